@@ -0,0 +1,73 @@
+//! Throughput of the scanners on large generated inputs.
+//! Compare with a previous run: `cargo bench -- --save-baseline before`
+//! then `cargo bench -- --baseline before`.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use scan_rs::csv;
+use scan_rs::sql;
+use scan_rs::{Liner, Scanner, Splitter};
+
+const SIZE: usize = 8 * 1024 * 1024;
+
+fn csv_data() -> Vec<u8> {
+    let mut data = Vec::with_capacity(SIZE);
+    let mut i = 0;
+    while data.len() < SIZE {
+        data.extend_from_slice(
+            format!(
+                "{},\"name {}\",\"quoted \"\"value\"\"\",{}.{},some unquoted text\r\n",
+                i,
+                i,
+                i * 7,
+                i % 100
+            )
+            .as_bytes(),
+        );
+        i += 1;
+    }
+    data
+}
+
+fn sql_data() -> Vec<u8> {
+    let mut data = Vec::with_capacity(SIZE);
+    let mut i = 0;
+    while data.len() < SIZE {
+        data.extend_from_slice(
+            format!(
+                "-- row {}\nINSERT INTO t (id, name, value) VALUES ({}, 'it''s {}', {}.5e3);\n",
+                i, i, i, i
+            )
+            .as_bytes(),
+        );
+        i += 1;
+    }
+    data
+}
+
+fn count<S: Splitter>(data: &[u8], splitter: S) -> usize {
+    let mut s = Scanner::new(data, splitter);
+    let mut n = 0;
+    while s.scan().unwrap().is_some() {
+        n += 1;
+    }
+    n
+}
+
+fn bench(c: &mut Criterion) {
+    let csv = csv_data();
+    let sql = sql_data();
+
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(csv.len() as u64));
+    group.bench_function("csv_fields", |b| b.iter(|| count(&csv, csv::Reader::new())));
+    group.bench_function("csv_lines", |b| b.iter(|| count(&csv, Liner::new())));
+    group.throughput(Throughput::Bytes(sql.len() as u64));
+    group.bench_function("sql_tokens", |b| {
+        b.iter(|| count(&sql, sql::Tokenizer::new()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -12,7 +12,7 @@ fn main() {
         let reader = Reader::new();
         let mut s = Scanner::new(f, reader);
         let mut counter = 0;
-        while let Some(_) = s.scan().unwrap() {
+        while s.scan().unwrap().is_some() {
             if s.splitter().end_of_record() {
                 counter += 1;
             }
@@ -15,10 +15,10 @@ fn main() {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
 
-    let arg = env::args().last().expect("One argument expected");
+    let arg = env::args().next_back().expect("One argument expected");
     println!("{:?}", arg);
     let f = File::open(arg).unwrap();
-    let liner = Liner {};
+    let liner = Liner::new();
     let mut s = Scanner::new(f, liner);
     loop {
         let field = s.scan().unwrap();
@@ -5,7 +5,6 @@ use scan::sql::Tokenizer;
 use scan::Scanner;
 use std::env;
 use std::fs::File;
-use std::i64;
 use std::str;
 
 fn main() {
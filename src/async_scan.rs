@@ -0,0 +1,223 @@
+//! Asynchronous scanner over a `tokio::io::AsyncRead`.
+use std::cmp;
+use std::fmt;
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::scan::{
+    split_range, ColumnMode, Position, ScanError, ScanResult, Span, Splitter, START_BUF_SIZE,
+};
+
+/// Like `Scanner` but over an `AsyncRead`.
+/// Successive calls to the `scan` method will step through the 'tokens'
+/// of a stream, skipping the bytes between the tokens.
+pub struct AsyncScanner<R: AsyncRead + Unpin, S: Splitter> {
+    /// The reader provided by the client.
+    inner: R,
+    /// The function to tokenize the input.
+    splitter: S,
+    /// Buffer used as argument to split.
+    buf: Vec<u8>,
+    /// First non-processed byte in buf.
+    pos: usize,
+    /// End of data in buf.
+    cap: usize,
+    eof: bool,
+    /// current line and column numbers
+    position: Position,
+    /// span of the last token
+    span: Span,
+    /// maximum size of a token (the buffer will not grow beyond)
+    max_token_size: usize,
+}
+
+impl<R: AsyncRead + Unpin, S: Splitter> AsyncScanner<R, S> {
+    pub fn new(inner: R, splitter: S) -> AsyncScanner<R, S> {
+        Self::with_capacity(inner, splitter, START_BUF_SIZE)
+    }
+
+    /// Create a scanner with an initial buffer of `capacity` bytes.
+    /// The buffer still grows when a token does not fit.
+    pub fn with_capacity(inner: R, splitter: S, capacity: usize) -> AsyncScanner<R, S> {
+        AsyncScanner {
+            inner,
+            splitter,
+            buf: vec![0; capacity],
+            pos: 0,
+            cap: 0,
+            eof: false,
+            position: Position::new(),
+            span: Span::default(),
+            max_token_size: usize::MAX,
+        }
+    }
+
+    /// Current line number
+    pub fn line(&self) -> u64 {
+        self.position.line
+    }
+
+    /// Current column number (see `ColumnMode`)
+    pub fn column(&self) -> usize {
+        self.position.column
+    }
+
+    /// Set how columns are counted (one per byte by default).
+    pub fn set_column_mode(&mut self, column_mode: ColumnMode) {
+        self.position.column_mode = column_mode;
+    }
+
+    /// Set the tab stops width used to count columns
+    /// (a tab counts as one column by default).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.position.set_tab_width(tab_width);
+    }
+
+    /// Current byte offset in the input (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.position.offset
+    }
+
+    /// Span of the last token returned by `scan`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Maximum size of a token (unbounded by default)
+    pub fn max_token_size(&self) -> usize {
+        self.max_token_size
+    }
+
+    /// Set the maximum size of a token.
+    /// See `Scanner::set_max_token_size`.
+    pub fn set_max_token_size(&mut self, max_token_size: usize) {
+        self.max_token_size = max_token_size;
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    /// Unwrap this scanner, returning the reader, the splitter and the buffer.
+    /// Any leftover data in the buffer is lost.
+    pub fn into_parts(self) -> (R, S, Vec<u8>) {
+        (self.inner, self.splitter, self.buf)
+    }
+
+    /// Advance the scanner to next token.
+    /// Return the token as a byte slice.
+    /// Return `None` when the end of the input is reached.
+    /// Return any error that occurs while reading the input.
+    pub async fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        debug!(target: "scanner", "scan(line: {}, column: {})", self.line(), self.column());
+        // Loop until we have a token.
+        loop {
+            // See if we can get a token with what we already have.
+            if self.cap > self.pos || self.eof {
+                let data = &mut self.buf[self.pos..self.cap];
+                match split_range(&mut self.splitter, data, self.eof) {
+                    Err(mut e) => {
+                        e.position(self.line(), self.column(), self.offset());
+                        return Err(e);
+                    }
+                    Ok((None, 0)) => {
+                        // Request more data
+                    }
+                    Ok((None, amt)) => {
+                        // Ignore/skip this data
+                        self.consume(amt);
+                        continue;
+                    }
+                    Ok((Some((range, token_type)), amt)) => {
+                        let start = self.pos;
+                        let position = self.position;
+                        self.consume(amt);
+                        self.span = Span::new(&position, &self.position);
+                        let token = &self.buf[start + range.start..start + range.end];
+                        return Ok(Some((token, token_type)));
+                    }
+                }
+            }
+            // We cannot generate a token with what we are holding.
+            // If we've already hit EOF, we are done.
+            if self.eof {
+                // Shut it down.
+                self.pos = 0;
+                self.cap = 0;
+                return Ok(None);
+            }
+            // Is the buffer full of a single token?
+            if self.pos == 0 && self.cap == self.buf.len() && self.cap >= self.max_token_size {
+                let mut e = S::Error::token_too_long();
+                e.position(self.line(), self.column(), self.offset());
+                return Err(e);
+            }
+            // Must read more data.
+            self.fill_buf().await?;
+        }
+    }
+
+    async fn fill_buf(&mut self) -> io::Result<()> {
+        debug!(target: "scanner", "fill_buf: pos: {}, cap: {}, buf: {}", self.pos, self.cap, self.buf.len());
+        // First, shift data to beginning of buffer if there's lots of empty space
+        // or space is needed.
+        if self.pos > 0 && (self.cap == self.buf.len() || self.pos > self.buf.len() / 2) {
+            self.buf.copy_within(self.pos..self.cap, 0);
+            self.cap -= self.pos;
+            self.pos = 0
+        }
+        // Is the buffer full? If so, resize.
+        if self.cap == self.buf.len() {
+            if self.buf.len() >= self.max_token_size {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "token too long"));
+            }
+            let len = match self.buf.len() {
+                0 => START_BUF_SIZE,
+                len => len.saturating_mul(2),
+            };
+            let len = cmp::min(len, self.max_token_size);
+            self.buf.resize(len, 0);
+        }
+        // Finally we can read some input.
+        loop {
+            match self.inner.read(&mut self.buf[self.cap..]).await {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.cap += n;
+                    break;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Consume `amt` bytes of the buffer.
+    fn consume(&mut self, amt: usize) {
+        debug!(target: "scanner", "consume({})", amt);
+        debug_assert!(self.pos + amt <= self.cap);
+        self.position.advance(&self.buf[self.pos..self.pos + amt]);
+        self.pos += amt;
+    }
+}
+
+impl<R: AsyncRead + Unpin, S: Splitter> fmt::Debug for AsyncScanner<R, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncScanner")
+            .field("buf", &self.buf)
+            .field("pos", &self.pos)
+            .field("cap", &self.cap)
+            .field("eof", &self.eof)
+            .field("line", &self.line())
+            .field("column", &self.column())
+            .field("max_token_size", &self.max_token_size)
+            .finish()
+    }
+}
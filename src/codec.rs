@@ -0,0 +1,131 @@
+//! Adapter from `Splitter` to `tokio_util::codec::Decoder`.
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::Decoder;
+
+use crate::scan::{split_range, ColumnMode, Position, ScanError, Splitter};
+
+/// Decoder yielding the tokens found by the splitter `S`.
+/// Tokens are returned as owned `BytesMut` with their `TokenType`.
+///
+/// `decode` calls the splitter with `eof` unset and `decode_eof` with `eof`
+/// set.
+pub struct SplitterCodec<S: Splitter> {
+    /// The function to tokenize the input.
+    splitter: S,
+    /// current line and column numbers
+    position: Position,
+    /// maximum size of a token
+    max_token_size: usize,
+}
+
+impl<S: Splitter> SplitterCodec<S> {
+    pub fn new(splitter: S) -> SplitterCodec<S> {
+        SplitterCodec {
+            splitter,
+            position: Position::new(),
+            max_token_size: usize::MAX,
+        }
+    }
+
+    /// Current line number
+    pub fn line(&self) -> u64 {
+        self.position.line
+    }
+
+    /// Current column number (see `ColumnMode`)
+    pub fn column(&self) -> usize {
+        self.position.column
+    }
+
+    /// Set how columns are counted (one per byte by default).
+    pub fn set_column_mode(&mut self, column_mode: ColumnMode) {
+        self.position.column_mode = column_mode;
+    }
+
+    /// Set the tab stops width used to count columns
+    /// (a tab counts as one column by default).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.position.set_tab_width(tab_width);
+    }
+
+    /// Current byte offset in the stream (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.position.offset
+    }
+
+    /// Maximum size of a token (unbounded by default)
+    pub fn max_token_size(&self) -> usize {
+        self.max_token_size
+    }
+
+    /// Set the maximum size of a token: when more data is needed but the
+    /// buffered data is already `max_token_size` long,
+    /// `ScanError::token_too_long` is returned.
+    pub fn set_max_token_size(&mut self, max_token_size: usize) {
+        self.max_token_size = max_token_size;
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    pub fn into_inner(self) -> S {
+        self.splitter
+    }
+
+    fn split(
+        &mut self,
+        src: &mut BytesMut,
+        eof: bool,
+    ) -> Result<Option<(BytesMut, S::TokenType)>, S::Error> {
+        loop {
+            if src.is_empty() && !eof {
+                // The splitter is never called with an empty data slice unless at EOF.
+                return Ok(None);
+            }
+            match split_range(&mut self.splitter, &mut src[..], eof) {
+                Err(mut e) => {
+                    e.position(self.line(), self.column(), self.offset());
+                    return Err(e);
+                }
+                Ok((None, 0)) => {
+                    if eof {
+                        // No more token
+                        src.clear();
+                    } else if src.len() >= self.max_token_size {
+                        let mut e = S::Error::token_too_long();
+                        e.position(self.line(), self.column(), self.offset());
+                        return Err(e);
+                    }
+                    // else request more data
+                    return Ok(None);
+                }
+                Ok((None, amt)) => {
+                    // Ignore/skip this data
+                    self.position.advance(&src[..amt]);
+                    src.advance(amt);
+                }
+                Ok((Some((range, token_type)), amt)) => {
+                    self.position.advance(&src[..amt]);
+                    let mut token = src.split_to(amt);
+                    token.truncate(range.end);
+                    token.advance(range.start);
+                    return Ok(Some((token, token_type)));
+                }
+            }
+        }
+    }
+}
+
+impl<S: Splitter> Decoder for SplitterCodec<S> {
+    type Item = (BytesMut, S::TokenType);
+    type Error = S::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, S::Error> {
+        self.split(src, false)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, S::Error> {
+        self.split(src, true)
+    }
+}
@@ -0,0 +1,234 @@
+//! Splitter adapters (see `Splitter::map_token`, `Splitter::filter_tokens`
+//! and `Splitter::skip_types`).
+//!
+//! Tokens are still slices of the input (no copy) and errors are those of
+//! the underlying splitter (such that their position is set by the scanner).
+use std::fmt;
+
+use crate::parallel::Resync;
+use crate::scan::{SplitResult, Splitter};
+
+/// Splitter mapping the token types of another splitter.
+#[derive(Clone)]
+pub struct MapToken<S, F> {
+    splitter: S,
+    f: F,
+}
+
+impl<S, F> MapToken<S, F> {
+    pub(crate) fn new(splitter: S, f: F) -> MapToken<S, F> {
+        MapToken { splitter, f }
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    pub fn into_inner(self) -> S {
+        self.splitter
+    }
+}
+
+impl<S, F, T> Splitter for MapToken<S, F>
+where
+    S: Splitter,
+    F: FnMut(&[u8], S::TokenType) -> T,
+{
+    type Error = S::Error;
+    type TokenType = T;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> SplitResult<'input, T, S::Error> {
+        let (tok, amt) = self.splitter.split(data, eof)?;
+        let f = &mut self.f;
+        Ok((
+            tok.map(|(token, token_type)| (token, f(token, token_type))),
+            amt,
+        ))
+    }
+
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        self.splitter.recover(data, eof)
+    }
+}
+
+impl<S, F, T> Resync for MapToken<S, F>
+where
+    S: Resync,
+    F: FnMut(&[u8], S::TokenType) -> T,
+{
+    fn resync(&self, data: &[u8]) -> Option<usize> {
+        self.splitter.resync(data)
+    }
+
+    fn end_of_record(&self) -> bool {
+        self.splitter.end_of_record()
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for MapToken<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MapToken")
+            .field("splitter", &self.splitter)
+            .finish()
+    }
+}
+
+/// Splitter skipping the tokens of another splitter which do not match a
+/// predicate.
+#[derive(Clone)]
+pub struct FilterTokens<S, P> {
+    splitter: S,
+    predicate: P,
+}
+
+impl<S, P> FilterTokens<S, P> {
+    pub(crate) fn new(splitter: S, predicate: P) -> FilterTokens<S, P> {
+        FilterTokens {
+            splitter,
+            predicate,
+        }
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    pub fn into_inner(self) -> S {
+        self.splitter
+    }
+}
+
+impl<S, P> Splitter for FilterTokens<S, P>
+where
+    S: Splitter,
+    P: FnMut(&[u8], &S::TokenType) -> bool,
+{
+    type Error = S::Error;
+    type TokenType = S::TokenType;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> SplitResult<'input, S::TokenType, S::Error> {
+        let (tok, amt) = self.splitter.split(data, eof)?;
+        let predicate = &mut self.predicate;
+        // A filtered out token is skipped like a separator.
+        Ok((
+            tok.filter(|(token, token_type)| predicate(token, token_type)),
+            amt,
+        ))
+    }
+
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        self.splitter.recover(data, eof)
+    }
+}
+
+impl<S, P> Resync for FilterTokens<S, P>
+where
+    S: Resync,
+    P: FnMut(&[u8], &S::TokenType) -> bool,
+{
+    fn resync(&self, data: &[u8]) -> Option<usize> {
+        self.splitter.resync(data)
+    }
+
+    fn end_of_record(&self) -> bool {
+        self.splitter.end_of_record()
+    }
+}
+
+impl<S: fmt::Debug, P> fmt::Debug for FilterTokens<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilterTokens")
+            .field("splitter", &self.splitter)
+            .finish()
+    }
+}
+
+/// Splitter skipping the tokens of another splitter with some types.
+pub struct SkipTypes<S: Splitter> {
+    splitter: S,
+    types: Vec<S::TokenType>,
+}
+
+impl<S: Splitter> SkipTypes<S> {
+    pub(crate) fn new(splitter: S, types: Vec<S::TokenType>) -> SkipTypes<S> {
+        SkipTypes { splitter, types }
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    pub fn into_inner(self) -> S {
+        self.splitter
+    }
+}
+
+impl<S: Splitter> Splitter for SkipTypes<S>
+where
+    S::TokenType: PartialEq,
+{
+    type Error = S::Error;
+    type TokenType = S::TokenType;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> SplitResult<'input, S::TokenType, S::Error> {
+        let (tok, amt) = self.splitter.split(data, eof)?;
+        let types = &self.types;
+        Ok((
+            tok.filter(|(_, token_type)| !types.contains(token_type)),
+            amt,
+        ))
+    }
+
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        self.splitter.recover(data, eof)
+    }
+}
+
+impl<S: Resync> Resync for SkipTypes<S>
+where
+    S::TokenType: PartialEq,
+{
+    fn resync(&self, data: &[u8]) -> Option<usize> {
+        self.splitter.resync(data)
+    }
+
+    fn end_of_record(&self) -> bool {
+        self.splitter.end_of_record()
+    }
+}
+
+impl<S: Splitter + Clone> Clone for SkipTypes<S>
+where
+    S::TokenType: Clone,
+{
+    fn clone(&self) -> SkipTypes<S> {
+        SkipTypes {
+            splitter: self.splitter.clone(),
+            types: self.types.clone(),
+        }
+    }
+}
+
+impl<S: Splitter + fmt::Debug> fmt::Debug for SkipTypes<S>
+where
+    S::TokenType: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SkipTypes")
+            .field("splitter", &self.splitter)
+            .field("types", &self.types)
+            .finish()
+    }
+}
@@ -2,7 +2,7 @@ use std::error;
 use std::fmt;
 use std::io;
 
-use scan::ScanError;
+use crate::scan::ScanError;
 
 /// Enum listing possible errors from Scanner.
 #[derive(Debug)]
@@ -11,9 +11,11 @@ pub enum Error {
     Io(io::Error),
     UnescapedQuote {
         quote: u8,
-        pos: Option<(u64, usize)>,
+        pos: Option<(u64, usize, u64)>,
     },
-    UnterminatedQuotedField(Option<(u64, usize)>),
+    UnterminatedQuotedField(Option<(u64, usize, u64)>),
+    /// Field too long to fit in the scanner buffer
+    TokenTooLong(Option<(u64, usize, u64)>),
 }
 
 impl fmt::Display for Error {
@@ -29,16 +31,19 @@ impl fmt::Display for Error {
             Error::UnterminatedQuotedField(pos) => {
                 write!(f, "non-terminated quoted field at {:?}", pos.unwrap())
             }
+            Error::TokenTooLong(pos) => write!(f, "token too long at {:?}", pos.unwrap()),
         }
     }
 }
 
 impl error::Error for Error {
+    #[allow(deprecated)]
     fn description(&self) -> &str {
         match *self {
             Error::Io(ref err) => err.description(),
             Error::UnescapedQuote { .. } => "Unescaped quote",
             Error::UnterminatedQuotedField(_) => "Unterminated quoted field",
+            Error::TokenTooLong(_) => "Token too long",
         }
     }
 }
@@ -50,11 +55,16 @@ impl From<io::Error> for Error {
 }
 
 impl ScanError for Error {
-    fn position(&mut self, line: u64, column: usize) {
+    fn position(&mut self, line: u64, column: usize, offset: u64) {
         match *self {
-            Error::UnescapedQuote { ref mut pos, .. } => *pos = Some((line, column)),
-            Error::UnterminatedQuotedField(ref mut pos) => *pos = Some((line, column)),
+            Error::UnescapedQuote { ref mut pos, .. } => *pos = Some((line, column, offset)),
+            Error::UnterminatedQuotedField(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::TokenTooLong(ref mut pos) => *pos = Some((line, column, offset)),
             _ => {}
         }
     }
+
+    fn token_too_long() -> Error {
+        Error::TokenTooLong(None)
+    }
 }
@@ -1,15 +1,17 @@
 //! Adaptation/port of
 //! [`SQLite` CSV parser](http://www.sqlite.org/src/artifact?ci=trunk&filename=src/shell.c).
 //! See `csv_read_one_field` function in `SQLite3` shell sources.
-use memchr::memchr2;
+use memchr::{memchr, memchr2, memchr2_iter};
 use std::ops::Range;
 use std::result::Result;
 
 mod error;
 
-pub use csv::error::Error;
-pub use scan::Splitter;
+pub use crate::csv::error::Error;
+use crate::parallel::Resync;
+pub use crate::scan::Splitter;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FieldType {
     /// Quoted value
     Quoted,
@@ -26,7 +28,7 @@ pub type Token<'input> = (&'input [u8], FieldType);
 /// other than ","). Successive calls to the `scan` method will step through
 /// the 'fields', skipping the separator/newline between the fields.
 /// The `end_of_record` method tells when a field is terminated by a line break.
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Reader {
     /// values separator
     sep: u8,
@@ -71,6 +73,16 @@ impl Reader {
         r
     }
 
+    /// Values separator
+    pub fn separator(&self) -> u8 {
+        self.sep
+    }
+
+    /// Whether values may be quoted
+    pub fn quoted(&self) -> bool {
+        self.quoted
+    }
+
     pub fn end_of_record(&self) -> bool {
         self.eor
     }
@@ -127,6 +139,45 @@ impl Splitter for Reader {
         // Request more data.
         Ok((None, 0))
     }
+
+    /// Skip to the next record.
+    /// A newline inside a quoted field does not end the record.
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        self.eor = true;
+        let end = if self.quoted && data.first() == Some(&b'"') {
+            self.resync(data)
+        } else {
+            memchr(b'\n', data).map(|i| i + 1)
+        };
+        match end {
+            Some(i) => Some(i),
+            None if eof => Some(data.len()),
+            None => Some(0),
+        }
+    }
+}
+
+/// Quote-parity speculation: the first newline preceded by an even number of
+/// quotes is assumed to be a record terminator.
+impl Resync for Reader {
+    fn resync(&self, data: &[u8]) -> Option<usize> {
+        if !self.quoted {
+            return memchr(b'\n', data).map(|i| i + 1);
+        }
+        let mut in_quotes = false;
+        for i in memchr2_iter(b'"', b'\n', data) {
+            if data[i] == b'"' {
+                in_quotes = !in_quotes;
+            } else if !in_quotes {
+                return Some(i + 1);
+            }
+        }
+        None
+    }
+
+    fn end_of_record(&self) -> bool {
+        self.eor
+    }
 }
 
 impl Reader {
@@ -0,0 +1,76 @@
+//! Transparent decompression of the input (gzip, zstd and bzip2, depending
+//! on the enabled features).
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+use crate::scan::{Scanner, Splitter};
+
+/// Compression formats detected from the magic bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Plain,
+}
+
+const MAGIC_LEN: usize = 4;
+
+impl Format {
+    fn detect(magic: &[u8]) -> Format {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Format::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Format::Zstd
+        } else if magic.len() == MAGIC_LEN
+            && magic.starts_with(b"BZh")
+            && (b'1'..=b'9').contains(&magic[3])
+        {
+            // "BZh" followed by the block size (1 to 9)
+            Format::Bzip2
+        } else {
+            Format::Plain
+        }
+    }
+}
+
+/// Detect the compression of `inner` from its first bytes and wrap it with
+/// the matching decoder.
+/// Uncompressed input is returned as is.
+///
+/// An error is returned if the input is compressed with a format whose
+/// feature (`gzip`, `zstd` or `bzip2`) is not enabled.
+pub fn decompress<R: Read + Send + 'static>(mut inner: R) -> io::Result<Box<dyn Read + Send>> {
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    (&mut inner)
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut magic)?;
+    let format = Format::detect(&magic);
+    debug!(target: "scanner", "decompress: {:?}", format);
+    // The magic bytes are given back to the decoder.
+    let inner = Cursor::new(magic).chain(inner);
+    match format {
+        #[cfg(feature = "gzip")]
+        Format::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(inner))),
+        #[cfg(feature = "zstd")]
+        Format::Zstd => Ok(Box::new(zstd::Decoder::new(inner)?)),
+        #[cfg(feature = "bzip2")]
+        Format::Bzip2 => Ok(Box::new(bzip2::read::MultiBzDecoder::new(inner))),
+        Format::Plain => Ok(Box::new(inner)),
+        #[allow(unreachable_patterns)]
+        format => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{:?} decompression is not enabled", format),
+        )),
+    }
+}
+
+impl<S: Splitter> Scanner<Box<dyn Read + Send>, S> {
+    /// Open the file at `path`, decompressing it if needed (see `decompress`).
+    /// Positions refer to the decompressed data.
+    pub fn open<P: AsRef<Path>>(path: P, splitter: S) -> io::Result<Self> {
+        let inner = decompress(File::open(path)?)?;
+        Ok(Scanner::new(inner, splitter))
+    }
+}
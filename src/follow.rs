@@ -0,0 +1,193 @@
+//! Scanner following a growing file (like `tail -F`).
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::scan::{ScanResult, Scanner, Span, Splitter};
+
+/// Identity of a file (device and inode numbers).
+/// Only available on Unix: rotation is not detected on other platforms.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Default delay between two checks for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Scan a file which is still being written to: at the end of the file,
+/// `scan` waits for new data instead of returning `None` for good.
+/// An incomplete last token (like a line without terminator) is not
+/// returned until it is complete.
+///
+/// The file is reopened (with a copy of the initial splitter) when:
+/// * it is truncated (its size is less than what has been read):
+///   scanning restarts from its beginning,
+/// * it is rotated (another file is found at its path, Unix only):
+///   the remaining data of the current file is scanned first.
+///
+/// Positions (line, column, offset) are relative to the current file.
+pub struct FollowScanner<S: Splitter> {
+    /// path of the followed file
+    path: PathBuf,
+    scanner: Scanner<File, S>,
+    /// identity of the current file
+    id: Option<(u64, u64)>,
+    /// file found at `path` after a rotation, scanned once the current one
+    /// is done
+    rotated: Option<File>,
+    /// initial splitter
+    splitter: S,
+    /// delay between two checks for new data
+    poll_interval: Duration,
+    /// how long `scan` waits for new data
+    timeout: Option<Duration>,
+}
+
+impl<S: Splitter + Clone> FollowScanner<S> {
+    /// Follow the file at `path` from its beginning.
+    pub fn open<P: AsRef<Path>>(path: P, splitter: S) -> io::Result<FollowScanner<S>> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let id = file_id(&file.metadata()?);
+        let mut scanner = Scanner::new(file, splitter.clone());
+        scanner.set_follow(true);
+        Ok(FollowScanner {
+            path,
+            scanner,
+            id,
+            rotated: None,
+            splitter,
+            poll_interval: POLL_INTERVAL,
+            timeout: None,
+        })
+    }
+
+    /// Path of the followed file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Set the delay between two checks for new data (250ms by default).
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Set how long `scan` waits for new data before returning `None`
+    /// (forever by default).
+    /// `scan` can be called again afterwards.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Current line number in the current file
+    pub fn line(&self) -> u64 {
+        self.scanner.line()
+    }
+
+    /// Current column number in the current file (see `ColumnMode`)
+    pub fn column(&self) -> usize {
+        self.scanner.column()
+    }
+
+    /// Current byte offset in the current file (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.scanner.offset()
+    }
+
+    /// Span of the last token returned by `scan` (in the current file)
+    pub fn span(&self) -> Span {
+        self.scanner.span()
+    }
+
+    pub fn splitter(&self) -> &S {
+        self.scanner.splitter()
+    }
+
+    /// Advance to next token, waiting for new data if needed.
+    /// Return `None` only when the timeout (see `set_timeout`) expires.
+    pub fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        let start = Instant::now();
+        loop {
+            if let Some((range, token_type)) = self.scanner.scan_range()? {
+                return Ok(Some((self.scanner.token(range), token_type)));
+            }
+            if let Some(file) = self.rotated.take() {
+                // The rotated file is done.
+                self.restart(file)?;
+                continue;
+            }
+            if self.check()? {
+                continue;
+            }
+            let elapsed = start.elapsed();
+            let wait = match self.timeout {
+                Some(timeout) if elapsed >= timeout => return Ok(None),
+                Some(timeout) => self.poll_interval.min(timeout - elapsed),
+                None => self.poll_interval,
+            };
+            thread::sleep(wait);
+        }
+    }
+
+    /// Check whether the file has been rotated or truncated.
+    fn check(&mut self) -> io::Result<bool> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Removed: wait for it to be created again.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if file_id(&metadata) != self.id {
+            let file = match File::open(&self.path) {
+                Ok(file) => file,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e),
+            };
+            debug!(target: "scanner", "rotated: {}", self.path.display());
+            // Scan what is left in the current file, up to its end.
+            self.scanner.set_follow(false);
+            self.rotated = Some(file);
+            return Ok(true);
+        }
+        let mut file = self.scanner.get_ref();
+        if metadata.len() < file.stream_position()? {
+            debug!(target: "scanner", "truncated: {}", self.path.display());
+            let mut file = file.try_clone()?;
+            file.seek(SeekFrom::Start(0))?;
+            self.restart(file)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Scan `file` from its current position, with the initial splitter.
+    fn restart(&mut self, file: File) -> io::Result<()> {
+        self.id = file_id(&file.metadata()?);
+        self.scanner.reset(file);
+        *self.scanner.splitter_mut() = self.splitter.clone();
+        self.scanner.set_follow(true);
+        Ok(())
+    }
+}
+
+impl<S: Splitter> fmt::Debug for FollowScanner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FollowScanner")
+            .field("path", &self.path)
+            .field("scanner", &self.scanner)
+            .field("rotated", &self.rotated.is_some())
+            .field("poll_interval", &self.poll_interval)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
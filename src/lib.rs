@@ -1,58 +1,65 @@
-#![feature(read_initializer)]
-#![feature(plugin)]
-#![plugin(phf_macros)]
-
+#[cfg(feature = "codec")]
+extern crate bytes;
+#[cfg(feature = "bzip2")]
+extern crate bzip2;
+#[cfg(feature = "fallible-streaming-iterator")]
+extern crate fallible_streaming_iterator;
+#[cfg(feature = "gzip")]
+extern crate flate2;
 #[macro_use]
 extern crate log;
 extern crate memchr;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[macro_use]
 extern crate phf;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "codec")]
+extern crate tokio_util;
+#[cfg(feature = "zstd")]
+extern crate zstd;
 
-use memchr::memchr;
 use std::io::Error;
-use std::result::Result;
 
+#[cfg(feature = "tokio")]
+mod async_scan;
+#[cfg(feature = "codec")]
+mod codec;
+mod combinator;
 pub mod csv;
+mod decompress;
+mod follow;
+mod liner;
+mod lookahead;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod multi;
+mod parallel;
+mod reverse;
 mod scan;
+mod slice;
 pub mod sql;
+mod text;
 
-pub use scan::{ScanError, Scanner, Splitter};
+#[cfg(feature = "tokio")]
+pub use async_scan::AsyncScanner;
+#[cfg(feature = "codec")]
+pub use codec::SplitterCodec;
+pub use combinator::{FilterTokens, MapToken, SkipTypes};
+pub use decompress::decompress;
+pub use follow::FollowScanner;
+pub use liner::{Line, Liner, LongLine, Terminator};
+pub use lookahead::{Lookahead, PeekResult};
+#[cfg(feature = "mmap")]
+pub use mmap::MmapScanner;
+pub use multi::{MultiScanner, SourceError, SourceResult};
+pub use parallel::{scan_parallel, ChunkScanner, Resync};
+pub use reverse::ReverseLiner;
+pub use scan::{Checkpoint, ColumnMode, IntoOwnedIter, ScanError, Scanner, Span, Splitter};
+pub use slice::SliceScanner;
+pub use text::{Fields, Runes, Words};
 
 impl ScanError for Error {
-    fn position(&mut self, _: u64, _: usize) {}
-}
-
-pub struct Liner {}
-pub type Line<'input> = (&'input [u8], ());
-
-impl Splitter for Liner {
-    type Error = Error;
-    type TokenType = ();
-
-    fn split<'input>(
-        &mut self,
-        data: &'input mut [u8],
-        eof: bool,
-    ) -> Result<(Option<Line<'input>>, usize), Error> {
-        debug!(target: "scanner", "scan_lines");
-        if eof && data.is_empty() {
-            return Ok((None, 0));
-        }
-        if let Some(i) = memchr(b'\n', data) {
-            return Ok((Some(drop_cr(&data[..i])), i + 1));
-        }
-        // If we're at EOF, we have a final, non-terminated line. Return it.
-        if eof {
-            return Ok((Some(drop_cr(data)), data.len()));
-        }
-        // Request more data.
-        Ok((None, 0))
-    }
-}
-
-// Drops a terminal \r from the data.
-fn drop_cr(data: &[u8]) -> (&[u8], ()) {
-    if !data.is_empty() && data[data.len() - 1] == b'\r' {
-        return (&data[..data.len() - 1], ());
-    }
-    (data, ())
+    fn position(&mut self, _: u64, _: usize, _: u64) {}
 }
@@ -0,0 +1,238 @@
+//! Line splitter.
+use std::io::{Error, ErrorKind};
+use std::result::Result;
+
+use memchr::{memchr, memchr2, memmem};
+
+use crate::parallel::Resync;
+use crate::scan::Splitter;
+
+/// What ended a line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Terminator {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// `\r` (only with universal newlines)
+    Cr,
+    /// custom delimiter (see `Liner::with_delimiter`)
+    Delimiter,
+    /// the input ended without terminator
+    Eof,
+    /// the line was truncated (see `Liner::max_line_length`)
+    /// and its remaining bytes are discarded
+    Truncated,
+}
+
+/// What to do with a line longer than `Liner::max_line_length`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongLine {
+    /// return an error (default)
+    Error,
+    /// return the beginning of the line and discard the rest
+    Truncate,
+    /// discard the whole line
+    Skip,
+}
+
+/// Split the input in lines.
+///
+/// By default, lines are terminated by `\n` and the terminator (with a
+/// preceding `\r`) is not part of the token.
+#[derive(Clone, Debug)]
+pub struct Liner {
+    /// custom delimiter (instead of newlines)
+    delimiter: Option<Vec<u8>>,
+    /// keep the terminator at the end of the token
+    pub keep_terminator: bool,
+    /// `\r`, `\n` and `\r\n` are all line terminators
+    /// (ignored with a custom delimiter)
+    pub universal_newlines: bool,
+    /// maximum line length (without the terminator), unbounded by default.
+    /// The scanner buffer does not grow much beyond.
+    pub max_line_length: usize,
+    /// what to do with a longer line
+    pub long_line: LongLine,
+    /// discarding the remaining bytes of a long line
+    discarding: bool,
+}
+
+pub type Line<'input> = (&'input [u8], Terminator);
+
+impl Liner {
+    pub fn new() -> Liner {
+        Liner {
+            delimiter: None,
+            keep_terminator: false,
+            universal_newlines: false,
+            max_line_length: usize::MAX,
+            long_line: LongLine::Error,
+            discarding: false,
+        }
+    }
+
+    /// Lines terminated by `delimiter` (one or more bytes, like `b"\0"` for
+    /// `find -print0` output) instead of newlines.
+    pub fn with_delimiter(delimiter: &[u8]) -> Liner {
+        assert!(!delimiter.is_empty(), "empty delimiter");
+        let mut liner = Liner::new();
+        liner.delimiter = Some(delimiter.to_vec());
+        liner
+    }
+
+    /// Custom delimiter
+    pub(crate) fn delimiter(&self) -> Option<&[u8]> {
+        self.delimiter.as_deref()
+    }
+
+    /// Maximum terminator length
+    fn max_terminator_len(&self) -> usize {
+        self.delimiter.as_ref().map_or(2, Vec::len)
+    }
+
+    /// Apply the `long_line` policy to the line at the start of `data`.
+    fn long_line<'input>(
+        &mut self,
+        data: &'input [u8],
+        eof: bool,
+    ) -> Result<(Option<Line<'input>>, usize), Error> {
+        debug!(target: "scanner", "long line: {:?}", self.long_line);
+        match self.long_line {
+            LongLine::Error => Err(Error::new(ErrorKind::InvalidData, "line too long")),
+            LongLine::Truncate => {
+                self.discarding = true;
+                let max = self.max_line_length;
+                Ok((Some((&data[..max], Terminator::Truncated)), max))
+            }
+            LongLine::Skip => Ok((None, self.discard(data, eof))),
+        }
+    }
+
+    /// Skip the remaining bytes of a long line (up to its terminator
+    /// included) and return how many bytes are skipped.
+    fn discard(&mut self, data: &[u8], eof: bool) -> usize {
+        if let Some((i, _, len)) = self.terminator(data, eof) {
+            self.discarding = false;
+            return i + len;
+        }
+        if eof {
+            self.discarding = false;
+            return data.len();
+        }
+        self.discarding = true;
+        // Keep what may be the beginning of a terminator.
+        data.len().saturating_sub(self.max_terminator_len() - 1)
+    }
+
+    /// Find the first terminator in `data`:
+    /// return its index, its type and its length.
+    fn terminator(&self, data: &[u8], eof: bool) -> Option<(usize, Terminator, usize)> {
+        if let Some(ref delimiter) = self.delimiter {
+            let i = if delimiter.len() == 1 {
+                memchr(delimiter[0], data)
+            } else {
+                memmem::find(data, delimiter)
+            }?;
+            return Some((i, Terminator::Delimiter, delimiter.len()));
+        }
+        if self.universal_newlines {
+            let i = memchr2(b'\r', b'\n', data)?;
+            if data[i] == b'\n' {
+                return Some((i, Terminator::Lf, 1));
+            }
+            return match data.get(i + 1) {
+                Some(b'\n') => Some((i, Terminator::CrLf, 2)),
+                Some(_) => Some((i, Terminator::Cr, 1)),
+                None if eof => Some((i, Terminator::Cr, 1)),
+                // Is it followed by a `\n`?
+                None => None,
+            };
+        }
+        let i = memchr(b'\n', data)?;
+        if i > 0 && data[i - 1] == b'\r' {
+            Some((i - 1, Terminator::CrLf, 2))
+        } else {
+            Some((i, Terminator::Lf, 1))
+        }
+    }
+}
+
+impl Default for Liner {
+    fn default() -> Liner {
+        Liner::new()
+    }
+}
+
+impl Splitter for Liner {
+    type Error = Error;
+    type TokenType = Terminator;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> Result<(Option<Line<'input>>, usize), Error> {
+        debug!(target: "scanner", "scan_lines");
+        if eof && data.is_empty() {
+            return Ok((None, 0));
+        }
+        if self.discarding {
+            return Ok((None, self.discard(data, eof)));
+        }
+        match self.terminator(data, eof) {
+            Some((i, terminator, len)) if i <= self.max_line_length => {
+                let end = if self.keep_terminator { i + len } else { i };
+                return Ok((Some((&data[..end], terminator)), i + len));
+            }
+            Some(_) => return self.long_line(data, eof),
+            None if data.len()
+                >= self
+                    .max_line_length
+                    .saturating_add(self.max_terminator_len()) =>
+            {
+                // No need to wait for the terminator.
+                return self.long_line(data, eof);
+            }
+            None => {}
+        }
+        // If we're at EOF, we have a final, non-terminated line. Return it.
+        if eof {
+            let end = if self.delimiter.is_none() && !self.keep_terminator {
+                drop_cr(data).len()
+            } else {
+                data.len()
+            };
+            if end > self.max_line_length {
+                return self.long_line(data, eof);
+            }
+            return Ok((Some((&data[..end], Terminator::Eof)), data.len()));
+        }
+        // Request more data.
+        Ok((None, 0))
+    }
+
+    /// Skip the rest of a too long line.
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        Some(self.discard(data, eof))
+    }
+}
+
+impl Resync for Liner {
+    fn resync(&self, data: &[u8]) -> Option<usize> {
+        // `eof` is not set such that a `\r` at the end of `data` is ambiguous
+        self.terminator(data, false).map(|(i, _, len)| i + len)
+    }
+
+    fn end_of_record(&self) -> bool {
+        true
+    }
+}
+
+// Drops a terminal \r from the data.
+fn drop_cr(data: &[u8]) -> &[u8] {
+    if !data.is_empty() && data[data.len() - 1] == b'\r' {
+        return &data[..data.len() - 1];
+    }
+    data
+}
@@ -0,0 +1,156 @@
+//! Token lookahead and pushback over a `Scanner`.
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Read;
+use std::mem;
+use std::ops::Range;
+
+use crate::scan::{ScanResult, Scanner, Span, Splitter};
+
+/// Result of `peek` and `peek_nth`.
+pub type PeekResult<'a, TokenType, Error> = Result<Option<(&'a [u8], &'a TokenType)>, Error>;
+
+/// Last token returned by `Lookahead::scan`.
+enum Last<TokenType> {
+    None,
+    /// Token still in the scanner buffer (no lookahead was needed).
+    Scanned(Range<usize>, TokenType, Span),
+    /// Token copied from the lookahead buffer.
+    Owned(Vec<u8>, TokenType, Span),
+}
+
+/// A `Scanner` with lookahead (`peek`, `peek_nth`) and pushback (`unscan`).
+///
+/// As long as no lookahead is requested, `scan` returns tokens borrowed from
+/// the scanner buffer, like `Scanner::scan`, without any copy.
+/// Peeked and pushed back tokens are copied in a lookahead buffer.
+/// Only their span is saved: the state of the splitter (see `scanner`) is
+/// the one after the last scanned token.
+pub struct Lookahead<R: Read, S: Splitter> {
+    scanner: Scanner<R, S>,
+    /// Tokens scanned ahead or pushed back, in order.
+    pending: VecDeque<(Vec<u8>, S::TokenType, Span)>,
+    last: Last<S::TokenType>,
+}
+
+impl<R: Read, S: Splitter> Lookahead<R, S>
+where
+    S::TokenType: Clone,
+{
+    pub fn new(scanner: Scanner<R, S>) -> Lookahead<R, S> {
+        Lookahead {
+            scanner,
+            pending: VecDeque::new(),
+            last: Last::None,
+        }
+    }
+
+    /// Span of the last token returned by `scan`
+    pub fn span(&self) -> Span {
+        match self.last {
+            Last::None => Span::default(),
+            Last::Scanned(_, _, span) | Last::Owned(_, _, span) => span,
+        }
+    }
+
+    /// The underlying scanner.
+    /// While tokens are pending (peeked or pushed back), its position and
+    /// its splitter state (like `csv::Reader::end_of_record`) are past them:
+    /// they do not describe the last token returned by `scan`.
+    pub fn scanner(&self) -> &Scanner<R, S> {
+        &self.scanner
+    }
+
+    /// Unwrap the underlying scanner.
+    /// Peeked and pushed back tokens are lost.
+    pub fn into_inner(self) -> Scanner<R, S> {
+        self.scanner
+    }
+
+    /// Advance to next token, consuming peeked and pushed back tokens first.
+    /// Return `None` when the end of the input is reached.
+    pub fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        if let Some((token, token_type, span)) = self.pending.pop_front() {
+            self.last = Last::Owned(token, token_type.clone(), span);
+            return Ok(self.last_token().map(|token| (token, token_type)));
+        }
+        self.last = Last::None;
+        match self.scanner.scan_range()? {
+            Some((range, token_type)) => {
+                let span = self.scanner.span();
+                self.last = Last::Scanned(range.clone(), token_type.clone(), span);
+                Ok(Some((self.scanner.token(range), token_type)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Return the next token without consuming it.
+    pub fn peek(&mut self) -> PeekResult<'_, S::TokenType, S::Error> {
+        self.peek_nth(0)
+    }
+
+    /// Return the `k`th next token (`peek_nth(0)` is `peek()`)
+    /// without consuming it.
+    /// Return `None` if the input ends before.
+    pub fn peek_nth(&mut self, k: usize) -> PeekResult<'_, S::TokenType, S::Error> {
+        while self.pending.len() <= k {
+            // The last token must be saved before the scanner buffer is reused.
+            self.last = match mem::replace(&mut self.last, Last::None) {
+                Last::Scanned(range, token_type, span) => {
+                    Last::Owned(self.scanner.token(range).to_vec(), token_type, span)
+                }
+                last => last,
+            };
+            match self.scanner.scan_range()? {
+                Some((range, token_type)) => {
+                    let token = self.scanner.token(range).to_vec();
+                    let span = self.scanner.span();
+                    self.pending.push_back((token, token_type, span));
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(self
+            .pending
+            .get(k)
+            .map(|(token, token_type, _)| (&token[..], token_type)))
+    }
+
+    /// Bytes of the last token returned by `scan`
+    fn last_token(&self) -> Option<&[u8]> {
+        match self.last {
+            Last::None => None,
+            Last::Scanned(ref range, _, _) => Some(self.scanner.token(range.clone())),
+            Last::Owned(ref token, _, _) => Some(token),
+        }
+    }
+
+    /// Push back the last token returned by `scan` such that the next call
+    /// to `scan` returns it again.
+    /// Only one token can be pushed back.
+    /// Return `false` if there is no token to push back.
+    pub fn unscan(&mut self) -> bool {
+        match mem::replace(&mut self.last, Last::None) {
+            Last::None => false,
+            Last::Scanned(range, token_type, span) => {
+                let token = self.scanner.token(range).to_vec();
+                self.pending.push_front((token, token_type, span));
+                true
+            }
+            Last::Owned(token, token_type, span) => {
+                self.pending.push_front((token, token_type, span));
+                true
+            }
+        }
+    }
+}
+
+impl<R: Read, S: Splitter> fmt::Debug for Lookahead<R, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lookahead")
+            .field("scanner", &self.scanner)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
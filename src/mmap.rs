@@ -0,0 +1,137 @@
+//! Scanner over a memory-mapped file.
+use std::fmt;
+use std::fs::File;
+use std::io;
+
+use memmap2::{MmapMut, MmapOptions};
+
+use crate::scan::{split_range, ColumnMode, Position, ScanError, ScanResult, Span, Splitter};
+
+/// Like `Scanner` but over a memory-mapped file instead of a `Read`:
+/// the whole file is given to the splitter at once (with `eof` set),
+/// without any copy.
+///
+/// The file is mapped privately (copy-on-write) so that splitters may
+/// modify the data in place (like `csv::Reader` unescaping quotes) without
+/// altering the file.
+pub struct MmapScanner<S: Splitter> {
+    /// The mapped file.
+    map: MmapMut,
+    /// The function to tokenize the input.
+    splitter: S,
+    /// First non-processed byte in map.
+    pos: usize,
+    /// current line and column numbers
+    position: Position,
+    /// span of the last token
+    span: Span,
+}
+
+impl<S: Splitter> MmapScanner<S> {
+    /// Map `file` privately.
+    ///
+    /// The file should not be modified (or truncated) by another process
+    /// while it is being scanned.
+    pub fn open(file: &File, splitter: S) -> io::Result<MmapScanner<S>> {
+        // SAFETY: the mapping is private, so our writes never reach the file,
+        // but changes made to the file by another process may still show
+        // through (undefined behaviour for the `&mut [u8]` given to the
+        // splitter) and a concurrent truncation makes accesses past the new
+        // end fault (SIGBUS). Like the documentation says, we assume the file
+        // is left alone while it is being scanned.
+        let map = unsafe { MmapOptions::new().map_copy(file)? };
+        Ok(Self::new(map, splitter))
+    }
+
+    pub fn new(map: MmapMut, splitter: S) -> MmapScanner<S> {
+        MmapScanner {
+            map,
+            splitter,
+            pos: 0,
+            position: Position::new(),
+            span: Span::default(),
+        }
+    }
+
+    /// Current line number
+    pub fn line(&self) -> u64 {
+        self.position.line
+    }
+
+    /// Current column number (see `ColumnMode`)
+    pub fn column(&self) -> usize {
+        self.position.column
+    }
+
+    /// Set how columns are counted (one per byte by default).
+    pub fn set_column_mode(&mut self, column_mode: ColumnMode) {
+        self.position.column_mode = column_mode;
+    }
+
+    /// Set the tab stops width used to count columns
+    /// (a tab counts as one column by default).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.position.set_tab_width(tab_width);
+    }
+
+    /// Current byte offset in the input (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.position.offset
+    }
+
+    /// Span of the last token returned by `scan`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    /// Unwrap this scanner, returning the mapping and the splitter.
+    pub fn into_parts(self) -> (MmapMut, S) {
+        (self.map, self.splitter)
+    }
+
+    /// Advance the scanner to next token.
+    /// Return the token as a slice of the mapping.
+    /// Return `None` when the end of the file is reached.
+    pub fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        debug!(target: "scanner", "scan(line: {}, column: {})", self.line(), self.column());
+        loop {
+            match split_range(&mut self.splitter, &mut self.map[self.pos..], true) {
+                Err(mut e) => {
+                    e.position(self.line(), self.column(), self.offset());
+                    return Err(e);
+                }
+                Ok((None, 0)) => {
+                    // No more token
+                    return Ok(None);
+                }
+                Ok((tok, amt)) => {
+                    let start = self.pos;
+                    let position = self.position;
+                    self.position.advance(&self.map[start..start + amt]);
+                    self.pos += amt;
+                    if let Some((range, token_type)) = tok {
+                        self.span = Span::new(&position, &self.position);
+                        let token = &self.map[start + range.start..start + range.end];
+                        return Ok(Some((token, token_type)));
+                    }
+                    // Ignore/skip this data
+                }
+            }
+        }
+    }
+}
+
+impl<S: Splitter> fmt::Debug for MmapScanner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MmapScanner")
+            .field("len", &self.map.len())
+            .field("pos", &self.pos)
+            .field("line", &self.line())
+            .field("column", &self.column())
+            .finish()
+    }
+}
@@ -0,0 +1,239 @@
+//! Scanner over a sequence of named readers.
+use std::error;
+use std::fmt;
+use std::io::Read;
+use std::iter::Fuse;
+use std::mem;
+
+use crate::scan::{ColumnMode, Scanner, Span, Splitter};
+
+/// Error with the name of the source and the position where it occurred.
+#[derive(Debug)]
+pub struct SourceError<E> {
+    /// name of the source (for example, a file name)
+    pub name: String,
+    /// line number in the source
+    pub line: u64,
+    /// column number in the source
+    pub column: usize,
+    pub error: E,
+}
+
+impl<E: fmt::Display> fmt::Display for SourceError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.name, self.line, self.column, self.error
+        )
+    }
+}
+
+impl<E: error::Error + 'static> error::Error for SourceError<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+pub type SourceResult<'input, TokenType, Error> =
+    Result<Option<(&'input [u8], TokenType)>, SourceError<Error>>;
+
+/// Scan a sequence of named readers, one after the other, as one stream of
+/// tokens.
+/// A token never spans two sources and the positions (line, column, offset)
+/// are relative to the current source.
+///
+/// The sources are pulled from the iterator only when needed, such that
+/// files can be opened lazily.
+/// Each source is scanned with a copy of the initial splitter, unless the
+/// splitter state is carried over (see `set_carry_state`).
+pub struct MultiScanner<R: Read, S: Splitter, I> {
+    /// scanner over the current source (`None` when there is no source)
+    scanner: Option<Scanner<R, S>>,
+    /// name of the current source
+    name: String,
+    /// remaining sources
+    sources: Fuse<I>,
+    /// initial splitter
+    splitter: S,
+    /// whether the splitter state is kept from one source to the next one
+    carry_state: bool,
+    /// errors collected in recovery mode in the previous sources
+    errors: Vec<(String, S::Error)>,
+}
+
+impl<R, S, I, N> MultiScanner<R, S, I>
+where
+    R: Read,
+    S: Splitter + Clone,
+    I: Iterator<Item = (N, R)>,
+    N: Into<String>,
+{
+    pub fn new<T>(sources: T, splitter: S) -> MultiScanner<R, S, I>
+    where
+        T: IntoIterator<Item = (N, R), IntoIter = I>,
+    {
+        let mut sources = sources.into_iter().fuse();
+        let (name, scanner) = match sources.next() {
+            Some((name, inner)) => (name.into(), Some(Scanner::new(inner, splitter.clone()))),
+            None => (String::new(), None),
+        };
+        MultiScanner {
+            scanner,
+            name,
+            sources,
+            splitter,
+            carry_state: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Keep the splitter state from one source to the next one
+    /// (by default, each source is scanned with a copy of the initial splitter).
+    pub fn set_carry_state(&mut self, carry_state: bool) {
+        self.carry_state = carry_state;
+    }
+
+    /// Name of the current source
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Current line number in the current source
+    pub fn line(&self) -> u64 {
+        self.scanner.as_ref().map_or(1, Scanner::line)
+    }
+
+    /// Current column number in the current source (see `ColumnMode`)
+    pub fn column(&self) -> usize {
+        self.scanner.as_ref().map_or(1, Scanner::column)
+    }
+
+    /// Set how columns are counted (one per byte by default).
+    pub fn set_column_mode(&mut self, column_mode: ColumnMode) {
+        if let Some(ref mut scanner) = self.scanner {
+            scanner.set_column_mode(column_mode);
+        }
+    }
+
+    /// Set the tab stops width used to count columns
+    /// (a tab counts as one column by default).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        if let Some(ref mut scanner) = self.scanner {
+            scanner.set_tab_width(tab_width);
+        }
+    }
+
+    /// Current byte offset in the current source (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.scanner.as_ref().map_or(0, Scanner::offset)
+    }
+
+    /// Span of the last token returned by `scan` (in the current source)
+    pub fn span(&self) -> Span {
+        self.scanner
+            .as_ref()
+            .map_or_else(Span::default, Scanner::span)
+    }
+
+    /// Set the maximum size of a token (see `Scanner::set_max_token_size`).
+    pub fn set_max_token_size(&mut self, max_token_size: usize) {
+        if let Some(ref mut scanner) = self.scanner {
+            scanner.set_max_token_size(max_token_size);
+        }
+    }
+
+    /// Collect the recoverable errors instead of returning them
+    /// (see `Scanner::set_error_recovery`).
+    pub fn set_error_recovery(&mut self, error_recovery: bool) {
+        if let Some(ref mut scanner) = self.scanner {
+            scanner.set_error_recovery(error_recovery);
+        }
+    }
+
+    /// Take the errors collected in recovery mode, with the name of their
+    /// source
+    pub fn take_errors(&mut self) -> Vec<(String, S::Error)> {
+        let mut errors = mem::take(&mut self.errors);
+        if let Some(ref mut scanner) = self.scanner {
+            let name = &self.name;
+            errors.extend(scanner.take_errors().into_iter().map(|e| (name.clone(), e)));
+        }
+        errors
+    }
+
+    /// Current splitter
+    pub fn splitter(&self) -> &S {
+        self.scanner
+            .as_ref()
+            .map_or(&self.splitter, Scanner::splitter)
+    }
+
+    /// Advance to next token, moving to the next source at the end of the
+    /// current one.
+    /// Return `None` when the end of the last source is reached.
+    pub fn scan(&mut self) -> SourceResult<'_, S::TokenType, S::Error> {
+        loop {
+            let scanner = match self.scanner {
+                Some(ref mut scanner) => scanner,
+                None => return Ok(None),
+            };
+            match scanner.scan_range() {
+                Ok(Some((range, token_type))) => {
+                    let scanner = self.scanner.as_ref().unwrap();
+                    return Ok(Some((scanner.token(range), token_type)));
+                }
+                Ok(None) => {
+                    if !self.next_source() {
+                        return Ok(None);
+                    }
+                }
+                Err(error) => {
+                    return Err(SourceError {
+                        name: self.name.clone(),
+                        line: scanner.line(),
+                        column: scanner.column(),
+                        error,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Move to the next source (skipping the rest of the current one, for
+    /// example after an error), reusing the buffer and settings of the
+    /// current scanner.
+    /// Return `false` when there is no more source.
+    pub fn next_source(&mut self) -> bool {
+        let (name, inner) = match self.sources.next() {
+            Some((name, inner)) => (name.into(), inner),
+            None => return false,
+        };
+        debug!(target: "scanner", "next source: {}", name);
+        if let Some(ref mut scanner) = self.scanner {
+            let previous = &self.name;
+            self.errors.extend(
+                scanner
+                    .take_errors()
+                    .into_iter()
+                    .map(|e| (previous.clone(), e)),
+            );
+            scanner.reset(inner);
+            if !self.carry_state {
+                *scanner.splitter_mut() = self.splitter.clone();
+            }
+        }
+        self.name = name;
+        true
+    }
+}
+
+impl<R: Read, S: Splitter, I> fmt::Debug for MultiScanner<R, S, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultiScanner")
+            .field("name", &self.name)
+            .field("scanner", &self.scanner)
+            .field("carry_state", &self.carry_state)
+            .finish()
+    }
+}
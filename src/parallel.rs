@@ -0,0 +1,229 @@
+//! Parallel scanning of a file split in chunks.
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::panic;
+use std::path::Path;
+use std::thread;
+
+use crate::scan::{Checkpoint, ScanResult, Scanner, Splitter, START_BUF_SIZE};
+
+/// A splitter whose tokens are grouped in records, such that scanning can
+/// start at any record boundary (with a fresh splitter).
+pub trait Resync: Splitter {
+    /// Speculative resynchronization: return the offset in `data` just after
+    /// the first record terminator, assuming that `data` does not start in
+    /// the middle of a quoted value.
+    /// Return `None` if there is no record terminator in `data`.
+    fn resync(&self, data: &[u8]) -> Option<usize>;
+
+    /// Tell if the last token terminated a record.
+    fn end_of_record(&self) -> bool;
+}
+
+/// Scanner over one chunk of a file (see `scan_parallel`).
+/// The chunk starts at a record boundary and stops after the first record
+/// which ends at or past its nominal end.
+///
+/// The offsets are absolute but the line numbers are relative to the start
+/// of the chunk.
+pub struct ChunkScanner<S: Splitter> {
+    scanner: Scanner<File, S>,
+    /// offset of the first record
+    start: u64,
+    /// nominal end of the chunk
+    limit: u64,
+    /// offset following the last record, once reached
+    end: Option<u64>,
+    /// no more token (end reached or error)
+    done: bool,
+}
+
+impl<S: Resync> ChunkScanner<S> {
+    fn open(path: &Path, splitter: S, start: u64, limit: u64) -> io::Result<ChunkScanner<S>> {
+        let checkpoint = Checkpoint {
+            offset: start,
+            line: 1,
+            column: 1,
+            splitter,
+        };
+        let scanner = Scanner::resume(File::open(path)?, checkpoint)?;
+        let done = start >= limit;
+        Ok(ChunkScanner {
+            scanner,
+            start,
+            limit,
+            end: if done { Some(start) } else { None },
+            done,
+        })
+    }
+
+    /// Offset of the first record of the chunk
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// The underlying scanner
+    pub fn scanner(&self) -> &Scanner<File, S> {
+        &self.scanner
+    }
+
+    /// Advance to next token of the chunk.
+    /// Return `None` when the end of the chunk is reached.
+    pub fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        if self.done {
+            return Ok(None);
+        }
+        let tok = match self.scanner.scan_range() {
+            Ok(tok) => tok,
+            Err(e) => {
+                self.done = true;
+                return Err(e);
+            }
+        };
+        match tok {
+            Some((range, token_type)) => {
+                if self.scanner.splitter().end_of_record() && self.scanner.offset() >= self.limit {
+                    self.done = true;
+                    self.end = Some(self.scanner.offset());
+                }
+                Ok(Some((self.scanner.token(range), token_type)))
+            }
+            None => {
+                self.done = true;
+                self.end = Some(self.scanner.offset());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Skip the remaining tokens and return the offset following the last
+    /// record of the chunk.
+    /// After an error, the chunk is scanned to its end in error recovery mode
+    /// (like a sequential scan would do) and `None` is returned if the error
+    /// cannot be recovered.
+    fn finish(&mut self) -> Option<u64> {
+        let mut recovering = false;
+        while self.end.is_none() {
+            if self.done {
+                if recovering {
+                    return None;
+                }
+                recovering = true;
+                self.scanner.set_error_recovery(true);
+                self.done = false;
+            }
+            let _ = self.scan();
+        }
+        self.end
+    }
+}
+
+impl<S: Splitter> fmt::Debug for ChunkScanner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChunkScanner")
+            .field("start", &self.start)
+            .field("limit", &self.limit)
+            .field("end", &self.end)
+            .field("scanner", &self.scanner)
+            .finish()
+    }
+}
+
+/// Find the first record boundary at or after `from` (speculatively, see
+/// `Resync::resync`).
+fn speculate<S: Resync>(path: &Path, splitter: &S, from: u64) -> io::Result<u64> {
+    if from == 0 {
+        return Ok(0);
+    }
+    let mut file = File::open(path)?;
+    // A boundary at `from` is preceded by a terminator.
+    file.seek(SeekFrom::Start(from - 1))?;
+    let mut buf = Vec::new();
+    let mut len = START_BUF_SIZE;
+    loop {
+        (&mut file)
+            .take((len - buf.len()) as u64)
+            .read_to_end(&mut buf)?;
+        if let Some(i) = splitter.resync(&buf) {
+            return Ok(from - 1 + i as u64);
+        }
+        if buf.len() < len {
+            // EOF: no more record
+            return Ok(from - 1 + buf.len() as u64);
+        }
+        len = len.saturating_mul(2);
+    }
+}
+
+/// Scan the file at `path` split in `chunks` byte ranges, each one on its
+/// own thread, and return the results of `f` for each chunk in order.
+///
+/// Each chunk start is resynchronized at a record boundary speculatively:
+/// when a speculation turns out to be wrong (the previous chunk does not end
+/// where the chunk starts), the chunk is scanned again from the right offset
+/// and the first result of `f` is discarded (so `f` may be called more than
+/// once for a chunk).
+/// A chunk with an error is scanned to its end in error recovery mode (see
+/// `Scanner::set_error_recovery`) to check where the next one starts. If the
+/// error cannot be recovered, the scan stops there like a sequential scan:
+/// the results of the following chunks are dropped.
+pub fn scan_parallel<P, S, F, T>(path: P, chunks: usize, splitter: S, f: F) -> io::Result<Vec<T>>
+where
+    P: AsRef<Path>,
+    S: Resync + Clone + Send,
+    F: Fn(&mut ChunkScanner<S>) -> T + Sync,
+    T: Send,
+{
+    let path = path.as_ref();
+    let len = path.metadata()?.len();
+    let chunks = chunks.max(1) as u64;
+    // nominal chunk boundaries
+    let limits: Vec<u64> = (0..chunks)
+        .map(|i| len / chunks * i)
+        .chain(Some(len))
+        .collect();
+
+    let f = &f;
+    let results: Vec<io::Result<(u64, Option<u64>, T)>> = thread::scope(|scope| {
+        let handles: Vec<_> = limits
+            .windows(2)
+            .map(|range| {
+                let (from, limit) = (range[0], range[1]);
+                let splitter = splitter.clone();
+                scope.spawn(move || {
+                    let start = speculate(path, &splitter, from)?;
+                    let mut chunk = ChunkScanner::open(path, splitter, start, limit)?;
+                    let result = f(&mut chunk);
+                    Ok((start, chunk.finish(), result))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+
+    let mut all = Vec::with_capacity(results.len());
+    let mut prev_end = 0;
+    for (result, range) in results.into_iter().zip(limits.windows(2)) {
+        let (start, end, result) = result?;
+        let end = if prev_end != start {
+            // Misspeculation: scan again from the right boundary.
+            debug!(target: "scanner", "chunk resynchronized at {} instead of {}", prev_end, start);
+            let mut chunk = ChunkScanner::open(path, splitter.clone(), prev_end, range[1])?;
+            all.push(f(&mut chunk));
+            chunk.finish()
+        } else {
+            all.push(result);
+            end
+        };
+        prev_end = match end {
+            Some(end) => end,
+            // The next chunk start cannot be checked.
+            None => break,
+        };
+    }
+    Ok(all)
+}
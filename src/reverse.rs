@@ -0,0 +1,233 @@
+//! Backward line scanner (like `tac` or `tail -n`).
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use memchr::{memmem, memrchr, memrchr2};
+
+use crate::liner::{Line, Liner, Terminator};
+use crate::scan::START_BUF_SIZE;
+
+/// Scan the lines of a seekable input from the last one to the first one,
+/// reading only the end of the input needed.
+///
+/// Lines are split like the given `Liner` (its delimiter, `universal_newlines`
+/// and `keep_terminator` options are honoured) but its `max_line_length`
+/// must be unbounded.
+/// A custom delimiter must not overlap itself (like `b"--"`): the lines
+/// could not be split as forward otherwise.
+pub struct ReverseLiner<R: Read + Seek> {
+    /// The reader provided by the client.
+    inner: R,
+    /// How lines are terminated.
+    liner: Liner,
+    /// Buffer filled backward: the data read is stored at its end.
+    buf: Vec<u8>,
+    /// First unprocessed byte in buf.
+    start: usize,
+    /// End of unprocessed data in buf.
+    end: usize,
+    /// Offset of `buf[start]` in the input (nothing left to read when 0).
+    pos: u64,
+    /// Offset of the last line returned by `scan`.
+    offset: u64,
+}
+
+impl<R: Read + Seek> ReverseLiner<R> {
+    pub fn new(inner: R, liner: Liner) -> io::Result<ReverseLiner<R>> {
+        Self::with_capacity(inner, liner, START_BUF_SIZE)
+    }
+
+    /// Create a scanner with an initial buffer of `capacity` bytes.
+    /// The buffer still grows when a line does not fit.
+    ///
+    /// An `InvalidInput` error is returned if `liner` cannot be used
+    /// backward (see above).
+    pub fn with_capacity(
+        mut inner: R,
+        liner: Liner,
+        capacity: usize,
+    ) -> io::Result<ReverseLiner<R>> {
+        if liner.max_line_length != usize::MAX {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "max_line_length is not supported backward",
+            ));
+        }
+        if let Some(delimiter) = liner.delimiter() {
+            if (1..delimiter.len()).any(|n| delimiter[..n] == delimiter[delimiter.len() - n..]) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "self-overlapping delimiter",
+                ));
+            }
+        }
+        let pos = inner.seek(SeekFrom::End(0))?;
+        Ok(ReverseLiner {
+            inner,
+            liner,
+            buf: vec![0; capacity],
+            start: capacity,
+            end: capacity,
+            pos,
+            offset: pos,
+        })
+    }
+
+    /// Byte offset of the last line returned by `scan` (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Unwrap this scanner, returning the reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Move to the previous line.
+    /// Return `None` when the start of the input is reached.
+    /// Only the last line may be terminated by `Terminator::Eof`.
+    pub fn scan(&mut self) -> io::Result<Option<Line<'_>>> {
+        loop {
+            if self.start == self.end {
+                if self.pos == 0 {
+                    return Ok(None);
+                }
+                self.fill_buf()?;
+                continue;
+            }
+            let data = &self.buf[self.start..self.end];
+            let (terminator, len) = match self.terminator(data, self.pos == 0) {
+                Some(terminator) => terminator,
+                None => {
+                    // We cannot tell how the line is terminated with what we are holding.
+                    self.fill_buf()?;
+                    continue;
+                }
+            };
+            let line_start = match self.previous_end(&data[..data.len() - len]) {
+                Some(i) => self.start + i,
+                None if self.pos > 0 => {
+                    // We cannot tell where the line starts with what we are holding.
+                    self.fill_buf()?;
+                    continue;
+                }
+                None => self.start,
+            };
+            let line_end = if self.liner.keep_terminator {
+                self.end
+            } else {
+                self.end - len
+            };
+            self.offset = self.pos + (line_start - self.start) as u64;
+            // The terminator before the line is the one of the previous line.
+            self.end = line_start;
+            let line = &self.buf[line_start..line_end];
+            return Ok(Some(match line.last() {
+                // Like `Liner`, a final `\r` is dropped.
+                Some(b'\r')
+                    if terminator == Terminator::Eof
+                        && self.liner.delimiter().is_none()
+                        && !self.liner.keep_terminator =>
+                {
+                    (&line[..line.len() - 1], terminator)
+                }
+                _ => (line, terminator),
+            }));
+        }
+    }
+
+    /// Find the terminator at the end of `data`: return its type and length.
+    /// Return `None` when it depends on the data preceding `data`
+    /// (unless `data` is `complete`).
+    fn terminator(&self, data: &[u8], complete: bool) -> Option<(Terminator, usize)> {
+        if let Some(delimiter) = self.liner.delimiter() {
+            return if data.ends_with(delimiter) {
+                Some((Terminator::Delimiter, delimiter.len()))
+            } else if data.len() < delimiter.len() && !complete {
+                None
+            } else {
+                Some((Terminator::Eof, 0))
+            };
+        }
+        match data {
+            [.., b'\r', b'\n'] => Some((Terminator::CrLf, 2)),
+            // Is it preceded by a `\r`?
+            [b'\n'] if !complete => None,
+            [.., b'\n'] => Some((Terminator::Lf, 1)),
+            [.., b'\r'] if self.liner.universal_newlines => Some((Terminator::Cr, 1)),
+            _ => Some((Terminator::Eof, 0)),
+        }
+    }
+
+    /// Find the end of the last terminator in `data`.
+    fn previous_end(&self, data: &[u8]) -> Option<usize> {
+        if let Some(delimiter) = self.liner.delimiter() {
+            let i = if delimiter.len() == 1 {
+                memrchr(delimiter[0], data)
+            } else {
+                memmem::rfind(data, delimiter)
+            }?;
+            return Some(i + delimiter.len());
+        }
+        let i = if self.liner.universal_newlines {
+            memrchr2(b'\r', b'\n', data)
+        } else {
+            memrchr(b'\n', data)
+        }?;
+        Some(i + 1)
+    }
+
+    /// Return (a copy of) the last `n` lines, in order.
+    pub fn tail(&mut self, n: usize) -> io::Result<Vec<Vec<u8>>> {
+        let mut lines = Vec::with_capacity(n);
+        while lines.len() < n {
+            match self.scan()? {
+                Some((line, _)) => lines.push(line.to_vec()),
+                None => break,
+            }
+        }
+        lines.reverse();
+        Ok(lines)
+    }
+
+    /// Read the data preceding the unprocessed data.
+    fn fill_buf(&mut self) -> io::Result<()> {
+        debug!(target: "scanner", "fill_buf: start: {}, end: {}, buf: {}", self.start, self.end, self.buf.len());
+        // Make room before the data.
+        if self.start == 0 {
+            // Is the buffer full? If so, resize.
+            if self.end == self.buf.len() {
+                let len = match self.buf.len() {
+                    0 => START_BUF_SIZE,
+                    len => len.saturating_mul(2),
+                };
+                self.buf.resize(len, 0);
+            }
+            // Shift data to the end of the buffer.
+            let len = self.buf.len();
+            self.buf.copy_within(..self.end, len - self.end);
+            self.start = len - self.end;
+            self.end = len;
+        }
+        // Finally we can read some input.
+        let n = self.pos.min(self.start as u64) as usize;
+        self.pos -= n as u64;
+        self.inner.seek(SeekFrom::Start(self.pos))?;
+        self.inner
+            .read_exact(&mut self.buf[self.start - n..self.start])?;
+        self.start -= n;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> fmt::Debug for ReverseLiner<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReverseLiner")
+            .field("liner", &self.liner)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("pos", &self.pos)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
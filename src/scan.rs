@@ -1,17 +1,34 @@
 //! Adaptation/port of [Go scanner](http://tip.golang.org/pkg/bufio/#Scanner).
+use std::cmp;
 use std::convert::From;
 use std::error::Error;
 use std::fmt;
-use std::io::{self, BufRead, Read};
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::mem;
+use std::ops::Range;
 use std::result::Result;
 
+#[cfg(feature = "fallible-streaming-iterator")]
+use fallible_streaming_iterator::FallibleStreamingIterator;
+use memchr::{memchr_iter, memrchr};
+
+use crate::combinator::{FilterTokens, MapToken, SkipTypes};
+
 pub trait ScanError: Error + From<io::Error> + Sized {
-    fn position(&mut self, line: u64, column: usize);
+    /// Set the position where the error occurred:
+    /// line and column numbers and absolute byte offset (first is 0).
+    fn position(&mut self, line: u64, column: usize, offset: u64);
+
+    /// Error returned when a token does not fit in the scanner buffer
+    /// (see `Scanner::set_max_token_size`).
+    fn token_too_long() -> Self {
+        Self::from(io::Error::new(io::ErrorKind::InvalidData, "token too long"))
+    }
 }
 
 /// The `(&[u8], TokenType)` is the token.
 /// And the `usize` is the amount of bytes to consume.
-type SplitResult<'input, TokenType, Error> =
+pub(crate) type SplitResult<'input, TokenType, Error> =
     Result<(Option<(&'input [u8], TokenType)>, usize), Error>;
 
 /// Split function used to tokenize the input
@@ -30,11 +47,215 @@ pub trait Splitter: Sized {
     /// The function is never called with an empty data slice unless at EOF.
     /// If `eof` is true, however, data may be non-empty and,
     /// as always, holds unprocessed text.
+    ///
+    /// A non-empty token must be a slice of `data` (not a copy, nor a
+    /// constant like `b"NULL"`): otherwise the scanner returns an
+    /// `InvalidData` error.
     fn split<'input>(
         &mut self,
         data: &'input mut [u8],
         eof: bool,
     ) -> SplitResult<'input, Self::TokenType, Self::Error>;
+
+    /// Called (in error recovery mode, see `Scanner::set_error_recovery`)
+    /// after `split` failed on `data`: return the amount of bytes to skip
+    /// to resynchronize, or `Some(0)` to request more data.
+    ///
+    /// Errors are not recoverable by default (`None`).
+    fn recover(&mut self, _data: &[u8], _eof: bool) -> Option<usize> {
+        None
+    }
+
+    /// Map the type of each token with `f` (given the token bytes and type).
+    fn map_token<F, T>(self, f: F) -> MapToken<Self, F>
+    where
+        F: FnMut(&[u8], Self::TokenType) -> T,
+    {
+        MapToken::new(self, f)
+    }
+
+    /// Skip the tokens for which `predicate` returns `false`.
+    fn filter_tokens<P>(self, predicate: P) -> FilterTokens<Self, P>
+    where
+        P: FnMut(&[u8], &Self::TokenType) -> bool,
+    {
+        FilterTokens::new(self, predicate)
+    }
+
+    /// Skip the tokens with one of the given `types`.
+    fn skip_types<I>(self, types: I) -> SkipTypes<Self>
+    where
+        I: IntoIterator<Item = Self::TokenType>,
+        Self::TokenType: PartialEq,
+    {
+        SkipTypes::new(self, types.into_iter().collect())
+    }
+}
+
+/// Same as `SplitResult` but with the token as a range in the input data.
+pub(crate) type SplitRangeResult<TokenType, Error> =
+    Result<(Option<(Range<usize>, TokenType)>, usize), Error>;
+
+/// Call `splitter` and return the token as a range in `data` such that
+/// `data` is not borrowed anymore when the function returns.
+/// The error position is not set.
+pub(crate) fn split_range<S: Splitter>(
+    splitter: &mut S,
+    data: &mut [u8],
+    eof: bool,
+) -> SplitRangeResult<S::TokenType, S::Error> {
+    let start = data.as_ptr() as usize;
+    let len = data.len();
+    let (tok, amt) = splitter.split(data, eof)?;
+    let tok = match tok {
+        None => None,
+        Some((&[], token_type)) => Some((0..0, token_type)),
+        Some((token, token_type)) => {
+            let offset = (token.as_ptr() as usize).wrapping_sub(start);
+            if offset > len || token.len() > len - offset {
+                return Err(S::Error::from(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "token is not a slice of the input data",
+                )));
+            }
+            Some((offset..offset + token.len(), token_type))
+        }
+    };
+    Ok((tok, amt))
+}
+
+/// How columns are counted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnMode {
+    /// One column per byte (default)
+    Bytes,
+    /// One column per UTF-8 encoded char
+    Chars,
+    /// One column per UTF-16 code unit (like LSP clients)
+    Utf16,
+}
+
+impl ColumnMode {
+    /// Number of columns for `byte` (continuation bytes do not count).
+    fn width(self, byte: u8) -> usize {
+        match self {
+            ColumnMode::Bytes => 1,
+            _ if byte & 0xC0 == 0x80 => 0,
+            ColumnMode::Utf16 if byte >= 0xF0 => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Line, column and byte offset of the next byte to be consumed.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Position {
+    /// current line number
+    pub line: u64,
+    /// current column number (see `ColumnMode`)
+    pub column: usize,
+    /// current byte offset in the stream (first is 0)
+    pub offset: u64,
+    /// how columns are counted
+    pub column_mode: ColumnMode,
+    /// tab stops width (a tab counts as one column when 1)
+    pub tab_width: usize,
+}
+
+impl Position {
+    pub fn new() -> Position {
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+            column_mode: ColumnMode::Bytes,
+            tab_width: 1,
+        }
+    }
+
+    /// Go back to the beginning of the stream (but keep the settings).
+    pub fn reset(&mut self) {
+        self.line = 1;
+        self.column = 1;
+        self.offset = 0;
+    }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        assert!(tab_width > 0, "tab width must be positive");
+        self.tab_width = tab_width;
+    }
+
+    /// Move after the consumed `data`.
+    pub fn advance(&mut self, data: &[u8]) {
+        self.offset += data.len() as u64;
+        // Only the bytes after the last newline matter for the column.
+        let tail = match memrchr(b'\n', data) {
+            Some(i) => {
+                self.line += memchr_iter(b'\n', &data[..i]).count() as u64 + 1;
+                self.column = 1;
+                &data[i + 1..]
+            }
+            None => data,
+        };
+        if self.column_mode == ColumnMode::Bytes && self.tab_width == 1 {
+            self.column += tail.len();
+            return;
+        }
+        for byte in tail {
+            if *byte == b'\t' {
+                // move to the next tab stop
+                self.column += self.tab_width - (self.column - 1) % self.tab_width;
+            } else {
+                self.column += self.column_mode.width(*byte);
+            }
+        }
+    }
+}
+
+/// Location of the input consumed by a token.
+/// Start is inclusive and end is exclusive.
+/// The span may include bytes which are not part of the token itself
+/// (quotes, separator, line terminator).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start_offset: u64,
+    pub end_offset: u64,
+    pub start_line: u64,
+    pub start_col: usize,
+    pub end_line: u64,
+    pub end_col: usize,
+}
+
+impl Span {
+    pub(crate) fn new(start: &Position, end: &Position) -> Span {
+        Span {
+            start_offset: start.offset,
+            end_offset: end.offset,
+            start_line: start.line,
+            start_col: start.column,
+            end_line: end.line,
+            end_col: end.column,
+        }
+    }
+}
+
+/// State of a `Scanner` at a token boundary (see `Scanner::checkpoint`),
+/// from which scanning can be resumed (see `Scanner::resume`).
+///
+/// To resume in another process, the splitter state must be saved along with
+/// the position. For `csv::Reader`, take the checkpoint at a record boundary
+/// (`end_of_record()`) such that the splitter can be rebuilt with
+/// `csv::Reader::custom(separator(), quoted())` (and the same public options).
+#[derive(Clone, Debug)]
+pub struct Checkpoint<S> {
+    /// byte offset of the first byte following the last token
+    pub offset: u64,
+    /// line number at `offset`
+    pub line: u64,
+    /// column number at `offset`
+    pub column: usize,
+    /// splitter state after the last token
+    pub splitter: S,
 }
 
 /// Like a `BufReader` but with a growable buffer.
@@ -56,23 +277,46 @@ pub struct Scanner<R: Read, S: Splitter> {
     /// End of data in buf.
     cap: usize,
     eof: bool,
-    /// current line number
-    line: u64,
-    /// current column number (byte offset, not char offset)
-    column: usize,
+    /// whether the end of the input is not final (see `set_follow`)
+    follow: bool,
+    /// current line and column numbers
+    position: Position,
+    /// span of the last token
+    span: Span,
+    /// maximum size of a token (the buffer will not grow beyond)
+    max_token_size: usize,
+    /// whether recoverable errors are collected instead of returned
+    error_recovery: bool,
+    /// skipping data after an error (see `Splitter::recover`)
+    recovering: bool,
+    /// errors collected in recovery mode
+    errors: Vec<S::Error>,
+    /// current token (as a range in buf) when scanning with `advance`
+    #[cfg(feature = "fallible-streaming-iterator")]
+    token: Option<(Range<usize>, S::TokenType)>,
 }
 
+/// Default initial size of the buffer.
+pub(crate) const START_BUF_SIZE: usize = 4096;
+
 impl<R: Read, S: Splitter> Scanner<R, S> {
     pub fn new(inner: R, splitter: S) -> Scanner<R, S> {
-        Self::with_capacity(inner, splitter, 4096)
+        Self::with_capacity(inner, splitter, START_BUF_SIZE)
     }
 
-    fn with_capacity(inner: R, splitter: S, capacity: usize) -> Scanner<R, S> {
-        let mut buf = Vec::with_capacity(capacity);
-        unsafe {
-            buf.set_len(capacity);
-            inner.initializer().initialize(&mut buf);
-        }
+    /// Create a scanner with an initial buffer of `capacity` bytes.
+    /// The buffer still grows when a token does not fit.
+    pub fn with_capacity(inner: R, splitter: S, capacity: usize) -> Scanner<R, S> {
+        Self::with_buffer(inner, splitter, Vec::with_capacity(capacity))
+    }
+
+    /// Create a scanner reusing the allocation of `buf`
+    /// (for example, one returned by `into_parts`).
+    /// Its content is discarded and its whole capacity is used.
+    pub fn with_buffer(inner: R, splitter: S, mut buf: Vec<u8>) -> Scanner<R, S> {
+        buf.clear();
+        let capacity = buf.capacity();
+        buf.resize(capacity, 0);
         Scanner {
             inner,
             splitter,
@@ -80,59 +324,209 @@ impl<R: Read, S: Splitter> Scanner<R, S> {
             pos: 0,
             cap: 0,
             eof: false,
-            line: 1,
-            column: 1,
+            follow: false,
+            position: Position::new(),
+            span: Span::default(),
+            max_token_size: usize::MAX,
+            error_recovery: false,
+            recovering: false,
+            errors: Vec::new(),
+            #[cfg(feature = "fallible-streaming-iterator")]
+            token: None,
         }
     }
 
     /// Current line number
     pub fn line(&self) -> u64 {
-        self.line
+        self.position.line
     }
 
-    /// Current column number (byte offset, not char offset)
+    /// Current column number (see `ColumnMode`)
     pub fn column(&self) -> usize {
-        self.column
+        self.position.column
+    }
+
+    /// Set how columns are counted (one per byte by default).
+    pub fn set_column_mode(&mut self, column_mode: ColumnMode) {
+        self.position.column_mode = column_mode;
+    }
+
+    /// Set the tab stops width used to count columns
+    /// (a tab counts as one column by default).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.position.set_tab_width(tab_width);
+    }
+
+    /// Current byte offset in the stream (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.position.offset
+    }
+
+    /// Span of the last token returned by `scan`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Maximum size of a token (unbounded by default)
+    pub fn max_token_size(&self) -> usize {
+        self.max_token_size
+    }
+
+    /// Set the maximum size of a token.
+    /// The actual maximum token size may be larger than `max_token_size`
+    /// if the buffer is already larger.
+    /// When a token does not fit, `scan` returns `ScanError::token_too_long`.
+    pub fn set_max_token_size(&mut self, max_token_size: usize) {
+        self.max_token_size = max_token_size;
     }
 
     pub fn splitter(&self) -> &S {
         &self.splitter
     }
 
+    pub(crate) fn splitter_mut(&mut self) -> &mut S {
+        &mut self.splitter
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// In error recovery mode, when the splitter can recover from an error
+    /// (see `Splitter::recover`), the error is collected (see `errors`)
+    /// and the scan goes on from the resynchronization point chosen by the
+    /// splitter instead of returning the error.
+    /// I/O errors are still returned.
+    pub fn set_error_recovery(&mut self, error_recovery: bool) {
+        self.error_recovery = error_recovery;
+    }
+
+    /// In follow mode, reaching the end of the input is not final (like
+    /// `tail -f`): `scan` returns `None` but an incomplete token is kept in
+    /// the buffer (not returned as the last one) and the next `scan` tries
+    /// to read more data.
+    /// Turn it off to get the last token once the input is complete.
+    /// See `FollowScanner` for growing files.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    /// Errors collected in recovery mode
+    pub fn errors(&self) -> &[S::Error] {
+        &self.errors
+    }
+
+    /// Take the errors collected in recovery mode
+    pub fn take_errors(&mut self) -> Vec<S::Error> {
+        mem::take(&mut self.errors)
+    }
+
+    /// Unwrap this scanner, returning the reader, the splitter and the buffer.
+    /// Any leftover data in the buffer is lost.
+    pub fn into_parts(self) -> (R, S, Vec<u8>) {
+        (self.inner, self.splitter, self.buf)
+    }
+
     /// Reset the scanner such that it behaves as if it had never been used.
     pub fn reset(&mut self, inner: R) {
         self.inner = inner;
         self.pos = 0;
         self.cap = 0;
         self.eof = false;
-        self.line = 1;
-        self.column = 1;
-        unsafe {
-            self.inner.initializer().initialize(&mut self.buf);
+        self.position.reset();
+        self.span = Span::default();
+        self.recovering = false;
+        self.errors.clear();
+        #[cfg(feature = "fallible-streaming-iterator")]
+        {
+            self.token = None;
         }
     }
 }
 
-type ScanResult<'input, TokenType, Error> = Result<Option<(&'input [u8], TokenType)>, Error>;
+impl<R: Read, S: Splitter + Clone> Scanner<R, S> {
+    /// Save the current state (just after the last token returned by `scan`).
+    /// The data read in advance in the buffer is not part of the checkpoint.
+    pub fn checkpoint(&self) -> Checkpoint<S> {
+        Checkpoint {
+            offset: self.position.offset,
+            line: self.position.line,
+            column: self.position.column,
+            splitter: self.splitter.clone(),
+        }
+    }
+}
+
+impl<R: Read + Seek, S: Splitter> Scanner<R, S> {
+    /// Create a scanner resuming from `checkpoint`:
+    /// `inner` is positioned at `checkpoint.offset` (from its start).
+    /// The column mode and tab width are not saved in a checkpoint and must be
+    /// set again (before scanning) for columns to be consistent.
+    pub fn resume(mut inner: R, checkpoint: Checkpoint<S>) -> io::Result<Scanner<R, S>> {
+        inner.seek(SeekFrom::Start(checkpoint.offset))?;
+        let mut scanner = Scanner::new(inner, checkpoint.splitter);
+        scanner.position.offset = checkpoint.offset;
+        scanner.position.line = checkpoint.line;
+        scanner.position.column = checkpoint.column;
+        Ok(scanner)
+    }
+}
+
+pub(crate) type ScanResult<'input, TokenType, Error> =
+    Result<Option<(&'input [u8], TokenType)>, Error>;
+/// Same as `ScanResult` but with the token as a range in the buffer.
+pub(crate) type ScanRangeResult<TokenType, Error> =
+    Result<Option<(Range<usize>, TokenType)>, Error>;
 
 impl<R: Read, S: Splitter> Scanner<R, S> {
     /// Advance the Scanner to next token.
     /// Return the token as a byte slice.
     /// Return `None` when the end of the input is reached.
     /// Return any error that occurs while reading the input.
-    pub fn scan<'input>(&'input mut self) -> ScanResult<'input, S::TokenType, S::Error> {
-        use std::mem;
-        debug!(target: "scanner", "scan(line: {}, column: {})", self.line, self.column);
+    pub fn scan(&mut self) -> ScanResult<'_, S::TokenType, S::Error> {
+        #[cfg(feature = "fallible-streaming-iterator")]
+        {
+            self.token = None;
+        }
+        let tok = self.scan_range()?;
+        Ok(tok.map(move |(range, token_type)| (&self.buf[range], token_type)))
+    }
+
+    /// Same as `scan` but the token is returned as a range in `buf`
+    /// (such that `self` is not borrowed by the token while looping).
+    pub(crate) fn scan_range(&mut self) -> ScanRangeResult<S::TokenType, S::Error> {
+        debug!(target: "scanner", "scan(line: {}, column: {})", self.line(), self.column());
         // Loop until we have a token.
         loop {
             // See if we can get a token with what we already have.
-            if self.cap > self.pos || self.eof {
-                // TODO: I don't know how to make the borrow checker happy!
-                let data = unsafe { mem::transmute(&mut self.buf[self.pos..self.cap]) };
-                match self.splitter.split(data, self.eof) {
+            if self.recovering && (self.cap > self.pos || self.eof) {
+                let data = &self.buf[self.pos..self.cap];
+                let amt = self.splitter.recover(data, self.eof).unwrap_or(0);
+                if self.resync(amt) {
+                    continue;
+                }
+                // Request more data
+            } else if self.cap > self.pos || self.eof {
+                let data = &mut self.buf[self.pos..self.cap];
+                match split_range(&mut self.splitter, data, self.eof) {
                     Err(mut e) => {
-                        e.position(self.line, self.column);
-                        return Err(e);
+                        e.position(self.line(), self.column(), self.offset());
+                        let amt = if self.error_recovery {
+                            let data = &self.buf[self.pos..self.cap];
+                            self.splitter.recover(data, self.eof)
+                        } else {
+                            None
+                        };
+                        let amt = match amt {
+                            Some(amt) => amt,
+                            None => return Err(e),
+                        };
+                        debug!(target: "scanner", "recover from: {}", e);
+                        self.errors.push(e);
+                        if self.resync(amt) {
+                            continue;
+                        }
+                        // Request more data
                     }
                     Ok((None, 0)) => {
                         // Request more data
@@ -142,9 +536,12 @@ impl<R: Read, S: Splitter> Scanner<R, S> {
                         self.consume(amt);
                         continue;
                     }
-                    Ok((tok, amt)) => {
+                    Ok((Some((range, token_type)), amt)) => {
+                        let start = self.pos;
+                        let position = self.position;
                         self.consume(amt);
-                        return Ok(tok);
+                        self.span = Span::new(&position, &self.position);
+                        return Ok(Some((start + range.start..start + range.end, token_type)));
                     }
                 }
             }
@@ -156,41 +553,149 @@ impl<R: Read, S: Splitter> Scanner<R, S> {
                 self.cap = 0;
                 return Ok(None);
             }
+            // Is the buffer full of a single token?
+            if self.pos == 0 && self.cap == self.buf.len() && self.cap >= self.max_token_size {
+                if self.recovering {
+                    // No resynchronization point yet: drop what we have.
+                    self.consume(self.cap);
+                    continue;
+                }
+                let mut e = S::Error::token_too_long();
+                e.position(self.line(), self.column(), self.offset());
+                return Err(e);
+            }
             // Must read more data.
+            let len = self.cap - self.pos;
             self.fill_buf()?;
+            if self.follow && self.cap - self.pos == len {
+                // Nothing more for now.
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Skip the `amt` bytes returned by `Splitter::recover` (or everything at
+    /// EOF) and return `true`, or return `false` if the splitter needs more
+    /// data to resynchronize.
+    fn resync(&mut self, amt: usize) -> bool {
+        if amt == 0 && !self.eof {
+            self.recovering = true;
+            return false;
+        }
+        // Skip the data up to the resynchronization point.
+        self.consume(if amt > 0 { amt } else { self.cap - self.pos });
+        self.recovering = false;
+        true
+    }
+
+    /// Bytes of a token returned by `scan_range`
+    /// (only valid until the buffer is refilled).
+    pub(crate) fn token(&self, range: Range<usize>) -> &[u8] {
+        &self.buf[range]
+    }
+}
+
+impl<R: Read, S: Splitter> Scanner<R, S> {
+    /// Convert this scanner into an `Iterator` over owned tokens
+    /// (each token is copied).
+    pub fn into_owned_iter(self) -> IntoOwnedIter<R, S> {
+        IntoOwnedIter {
+            scanner: self,
+            done: false,
         }
     }
 }
 
+/// Iterator over owned tokens (see `Scanner::into_owned_iter`).
+/// The iteration stops after the first error.
+pub struct IntoOwnedIter<R: Read, S: Splitter> {
+    scanner: Scanner<R, S>,
+    done: bool,
+}
+
+impl<R: Read, S: Splitter> IntoOwnedIter<R, S> {
+    /// Unwrap this iterator, returning the underlying scanner.
+    pub fn into_inner(self) -> Scanner<R, S> {
+        self.scanner
+    }
+}
+
+impl<R: Read, S: Splitter> Iterator for IntoOwnedIter<R, S> {
+    type Item = Result<(Vec<u8>, S::TokenType), S::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.scanner.scan() {
+            Ok(Some((token, token_type))) => Some(Ok((token.to_vec(), token_type))),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fallible-streaming-iterator")]
+impl<R: Read, S: Splitter> Scanner<R, S> {
+    /// Type of the current token (see `FallibleStreamingIterator::get`)
+    pub fn token_type(&self) -> Option<&S::TokenType> {
+        self.token.as_ref().map(|(_, token_type)| token_type)
+    }
+}
+
+/// `get` returns the bytes of the current token and `Scanner::token_type`
+/// its type.
+#[cfg(feature = "fallible-streaming-iterator")]
+impl<R: Read, S: Splitter> FallibleStreamingIterator for Scanner<R, S> {
+    type Item = [u8];
+    type Error = S::Error;
+
+    fn advance(&mut self) -> Result<(), S::Error> {
+        // No token after an error.
+        self.token = None;
+        self.token = self.scan_range()?;
+        Ok(())
+    }
+
+    fn get(&self) -> Option<&[u8]> {
+        self.token
+            .as_ref()
+            .map(|(range, _)| &self.buf[range.clone()])
+    }
+}
+
 impl<R: Read, S: Splitter> BufRead for Scanner<R, S> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
         debug!(target: "scanner", "fill_buf: pos: {}, cap: {}, buf: {}", self.pos, self.cap, self.buf.len());
+        // The buffer is going to be compacted.
+        #[cfg(feature = "fallible-streaming-iterator")]
+        {
+            self.token = None;
+        }
         // First, shift data to beginning of buffer if there's lots of empty space
         // or space is needed.
         if self.pos > 0 && (self.cap == self.buf.len() || self.pos > self.buf.len() / 2) {
-            unsafe {
-                use std::ptr;
-                ptr::copy(
-                    self.buf.as_mut_ptr().add(self.pos),
-                    self.buf.as_mut_ptr(),
-                    self.cap - self.pos,
-                );
-            }
+            self.buf.copy_within(self.pos..self.cap, 0);
             self.cap -= self.pos;
             self.pos = 0
         }
         // Is the buffer full? If so, resize.
         if self.cap == self.buf.len() {
-            // TODO maxTokenSize
-            let additional = self.buf.capacity();
-            self.buf.reserve(additional);
-            let cap = self.buf.capacity();
-            unsafe {
-                self.buf.set_len(cap);
-                self.inner
-                    .initializer()
-                    .initialize(&mut self.buf[self.cap..])
+            if self.buf.len() >= self.max_token_size {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "token too long"));
             }
+            let len = match self.buf.len() {
+                0 => START_BUF_SIZE,
+                len => len.saturating_mul(2),
+            };
+            let len = cmp::min(len, self.max_token_size);
+            self.buf.resize(len, 0);
             self.cap -= self.pos;
             self.pos = 0;
         }
@@ -198,7 +703,8 @@ impl<R: Read, S: Splitter> BufRead for Scanner<R, S> {
         loop {
             match self.inner.read(&mut self.buf[self.cap..]) {
                 Ok(0) => {
-                    self.eof = true;
+                    // In follow mode, the input may grow.
+                    self.eof = !self.follow;
                     break;
                 }
                 Ok(n) => {
@@ -217,15 +723,12 @@ impl<R: Read, S: Splitter> BufRead for Scanner<R, S> {
     /// Consume `amt` bytes of the buffer.
     fn consume(&mut self, amt: usize) {
         debug!(target: "scanner", "comsume({})", amt);
-        debug_assert!(self.pos + amt <= self.cap);
-        for byte in &self.buf[self.pos..self.pos + amt] {
-            if *byte == b'\n' {
-                self.line += 1;
-                self.column = 1;
-            } else {
-                self.column += 1;
-            }
+        #[cfg(feature = "fallible-streaming-iterator")]
+        {
+            self.token = None;
         }
+        debug_assert!(self.pos + amt <= self.cap);
+        self.position.advance(&self.buf[self.pos..self.pos + amt]);
         self.pos += amt;
     }
 }
@@ -248,8 +751,10 @@ impl<R: Read, S: Splitter> fmt::Debug for Scanner<R, S> {
             .field("pos", &self.pos)
             .field("cap", &self.cap)
             .field("eof", &self.eof)
-            .field("line", &self.line)
-            .field("column", &self.column)
+            .field("follow", &self.follow)
+            .field("line", &self.line())
+            .field("column", &self.column())
+            .field("max_token_size", &self.max_token_size)
             .finish()
     }
 }
@@ -0,0 +1,108 @@
+//! Scanner over an in-memory buffer.
+use std::fmt;
+use std::mem;
+
+use crate::scan::{split_range, ColumnMode, Position, ScanError, ScanResult, Span, Splitter};
+
+/// Like `Scanner` but over a mutable byte slice instead of a `Read`:
+/// there is no copy, the whole input is given to the splitter at once
+/// (with `eof` set) and the tokens borrow from the input.
+pub struct SliceScanner<'input, S: Splitter> {
+    /// Unprocessed input.
+    data: &'input mut [u8],
+    /// The function to tokenize the input.
+    splitter: S,
+    /// current line and column numbers
+    position: Position,
+    /// span of the last token
+    span: Span,
+}
+
+impl<'input, S: Splitter> SliceScanner<'input, S> {
+    pub fn new(data: &'input mut [u8], splitter: S) -> SliceScanner<'input, S> {
+        SliceScanner {
+            data,
+            splitter,
+            position: Position::new(),
+            span: Span::default(),
+        }
+    }
+
+    /// Current line number
+    pub fn line(&self) -> u64 {
+        self.position.line
+    }
+
+    /// Current column number (see `ColumnMode`)
+    pub fn column(&self) -> usize {
+        self.position.column
+    }
+
+    /// Set how columns are counted (one per byte by default).
+    pub fn set_column_mode(&mut self, column_mode: ColumnMode) {
+        self.position.column_mode = column_mode;
+    }
+
+    /// Set the tab stops width used to count columns
+    /// (a tab counts as one column by default).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.position.set_tab_width(tab_width);
+    }
+
+    /// Current byte offset in the input (first is 0)
+    pub fn offset(&self) -> u64 {
+        self.position.offset
+    }
+
+    /// Span of the last token returned by `scan`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    /// Advance the scanner to next token.
+    /// Return the token as a slice of the input.
+    /// Return `None` when the end of the input is reached.
+    pub fn scan(&mut self) -> ScanResult<'input, S::TokenType, S::Error> {
+        debug!(target: "scanner", "scan(line: {}, column: {})", self.line(), self.column());
+        loop {
+            let data = mem::take(&mut self.data);
+            match split_range(&mut self.splitter, &mut *data, true) {
+                Err(mut e) => {
+                    e.position(self.line(), self.column(), self.offset());
+                    self.data = data;
+                    return Err(e);
+                }
+                Ok((None, 0)) => {
+                    // No more token
+                    return Ok(None);
+                }
+                Ok((tok, amt)) => {
+                    let (head, tail) = data.split_at_mut(amt);
+                    let position = self.position;
+                    self.position.advance(head);
+                    self.data = tail;
+                    if let Some((range, token_type)) = tok {
+                        self.span = Span::new(&position, &self.position);
+                        let head: &'input [u8] = head;
+                        return Ok(Some((&head[range], token_type)));
+                    }
+                    // Ignore/skip this data
+                }
+            }
+        }
+    }
+}
+
+impl<'input, S: Splitter> fmt::Debug for SliceScanner<'input, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SliceScanner")
+            .field("data", &self.data)
+            .field("line", &self.line())
+            .field("column", &self.column())
+            .finish()
+    }
+}
@@ -2,21 +2,23 @@ use std::error;
 use std::fmt;
 use std::io;
 
-use scan::ScanError;
+use crate::scan::ScanError;
 
 #[derive(Debug)]
 pub enum Error {
     /// I/O Error
     Io(io::Error),
-    UnrecognizedToken(Option<(u64, usize)>),
-    UnterminatedLiteral(Option<(u64, usize)>),
-    UnterminatedBracket(Option<(u64, usize)>),
-    UnterminatedBlockComment(Option<(u64, usize)>),
-    BadVariableName(Option<(u64, usize)>),
-    BadNumber(Option<(u64, usize)>),
-    ExpectedEqualsSign(Option<(u64, usize)>),
-    MalformedBlobLiteral(Option<(u64, usize)>),
-    MalformedHexInteger(Option<(u64, usize)>),
+    UnrecognizedToken(Option<(u64, usize, u64)>),
+    UnterminatedLiteral(Option<(u64, usize, u64)>),
+    UnterminatedBracket(Option<(u64, usize, u64)>),
+    UnterminatedBlockComment(Option<(u64, usize, u64)>),
+    BadVariableName(Option<(u64, usize, u64)>),
+    BadNumber(Option<(u64, usize, u64)>),
+    ExpectedEqualsSign(Option<(u64, usize, u64)>),
+    MalformedBlobLiteral(Option<(u64, usize, u64)>),
+    MalformedHexInteger(Option<(u64, usize, u64)>),
+    /// Token too long to fit in the scanner buffer
+    TokenTooLong(Option<(u64, usize, u64)>),
 }
 
 impl fmt::Display for Error {
@@ -42,11 +44,13 @@ impl fmt::Display for Error {
             Error::MalformedHexInteger(pos) => {
                 write!(f, "malformed hex integer at {:?}", pos.unwrap())
             }
+            Error::TokenTooLong(pos) => write!(f, "token too long at {:?}", pos.unwrap()),
         }
     }
 }
 
 impl error::Error for Error {
+    #[allow(deprecated)]
     fn description(&self) -> &str {
         match *self {
             Error::Io(ref err) => err.description(),
@@ -59,6 +63,7 @@ impl error::Error for Error {
             Error::ExpectedEqualsSign(_) => "Expected = sign",
             Error::MalformedBlobLiteral(_) => "Malformed blob literal",
             Error::MalformedHexInteger(_) => "Malformed hex integer",
+            Error::TokenTooLong(_) => "Token too long",
         }
     }
 }
@@ -70,18 +75,23 @@ impl From<io::Error> for Error {
 }
 
 impl ScanError for Error {
-    fn position(&mut self, line: u64, column: usize) {
+    fn position(&mut self, line: u64, column: usize, offset: u64) {
         match *self {
             Error::Io(_) => {}
-            Error::UnrecognizedToken(ref mut pos) => *pos = Some((line, column)),
-            Error::UnterminatedLiteral(ref mut pos) => *pos = Some((line, column)),
-            Error::UnterminatedBracket(ref mut pos) => *pos = Some((line, column)),
-            Error::UnterminatedBlockComment(ref mut pos) => *pos = Some((line, column)),
-            Error::BadVariableName(ref mut pos) => *pos = Some((line, column)),
-            Error::BadNumber(ref mut pos) => *pos = Some((line, column)),
-            Error::ExpectedEqualsSign(ref mut pos) => *pos = Some((line, column)),
-            Error::MalformedBlobLiteral(ref mut pos) => *pos = Some((line, column)),
-            Error::MalformedHexInteger(ref mut pos) => *pos = Some((line, column)),
+            Error::UnrecognizedToken(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::UnterminatedLiteral(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::UnterminatedBracket(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::UnterminatedBlockComment(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::BadVariableName(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::BadNumber(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::ExpectedEqualsSign(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::MalformedBlobLiteral(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::MalformedHexInteger(ref mut pos) => *pos = Some((line, column, offset)),
+            Error::TokenTooLong(ref mut pos) => *pos = Some((line, column, offset)),
         }
     }
+
+    fn token_too_long() -> Error {
+        Error::TokenTooLong(None)
+    }
 }
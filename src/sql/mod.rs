@@ -6,8 +6,8 @@ use phf;
 
 mod error;
 
-pub use scan::Splitter;
-pub use sql::error::Error;
+pub use crate::scan::Splitter;
+pub use crate::sql::error::Error;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TokenType {
@@ -304,6 +304,7 @@ const MAX_KEYWORD_LEN: usize = 17;
 
 pub type Token<'input> = (&'input [u8], TokenType);
 
+#[derive(Clone, Debug)]
 pub struct Tokenizer {
     uppercase_buffer: [u8; MAX_KEYWORD_LEN],
 }
@@ -316,6 +317,12 @@ impl Tokenizer {
     }
 }
 
+impl Default for Tokenizer {
+    fn default() -> Tokenizer {
+        Tokenizer::new()
+    }
+}
+
 impl Splitter for Tokenizer {
     type Error = Error;
     type TokenType = TokenType;
@@ -457,7 +464,7 @@ impl Splitter for Tokenizer {
                     return Ok((Some((&data[..1], TokenType::Dot)), 1));
                 }
             } /* else ask more data */
-            b'0'...b'9' => return number(data, eof),
+            b'0'..=b'9' => return number(data, eof),
             b'[' => {
                 if let Some(i) = memchr(b']', data) {
                     // do not include the '['/']' in the token
@@ -472,7 +479,9 @@ impl Splitter for Tokenizer {
                         // do not include the '?' in the token
                         return Ok((Some((&data[1..=i], TokenType::Variable)), i + 1));
                     }
-                    None if eof => return Ok((Some((&data[1..], TokenType::Variable)), data.len())),
+                    None if eof => {
+                        return Ok((Some((&data[1..], TokenType::Variable)), data.len()))
+                    }
                     _ => {
                         // else ask more data
                     }
@@ -516,6 +525,19 @@ impl Splitter for Tokenizer {
         // Request more data.
         Ok((None, 0))
     }
+
+    /// Skip to the next whitespace or `;`.
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        match data
+            .iter()
+            .skip(1)
+            .position(|&b| b.is_ascii_whitespace() || b == b';')
+        {
+            Some(i) => Some(i + 1),
+            None if eof => Some(data.len()),
+            None => Some(0),
+        }
+    }
 }
 
 fn literal<'input>(
@@ -750,14 +772,12 @@ impl Tokenizer {
                 let upcase = if word.iter().all(|b| b.is_ascii_uppercase()) {
                     word
                 } else {
-                    let mut buffer = &mut self.uppercase_buffer[..word.len()];
+                    let buffer = &mut self.uppercase_buffer[..word.len()];
                     buffer.copy_from_slice(word);
                     buffer.make_ascii_uppercase();
                     buffer
                 };
-                // https://github.com/rust-lang/rust/issues/28853
-                let b = unsafe { ::std::mem::transmute::<_, &'static [u8]>(upcase) };
-                KEYWORDS.get(&b).cloned().unwrap_or(TokenType::Id)
+                KEYWORDS.get(upcase).cloned().unwrap_or(TokenType::Id)
             } else {
                 TokenType::Id
             };
@@ -769,14 +789,14 @@ impl Tokenizer {
 }
 
 fn is_identifier_start(b: u8) -> bool {
-    (b >= b'A' && b <= b'Z') || b == b'_' || (b >= b'a' && b <= b'z') || b > b'\x7F'
+    b.is_ascii_uppercase() || b == b'_' || b.is_ascii_lowercase() || b > b'\x7F'
 }
 
 fn is_identifier_continue(b: u8) -> bool {
     b == b'$'
-        || (b >= b'0' && b <= b'9')
-        || (b >= b'A' && b <= b'Z')
+        || b.is_ascii_digit()
+        || b.is_ascii_uppercase()
         || b == b'_'
-        || (b >= b'a' && b <= b'z')
+        || b.is_ascii_lowercase()
         || b > b'\x7F'
 }
@@ -0,0 +1,285 @@
+//! Word, rune and field splitters
+//! (see Go `bufio.ScanWords` and `bufio.ScanRunes`).
+use std::io::{Error, ErrorKind};
+use std::str;
+
+use memchr::{memchr, memchr2};
+
+use crate::parallel::Resync;
+use crate::scan::{SplitResult, Splitter};
+
+/// Decode the first char of `data` (which must not be empty).
+/// Return `Some((None, n))` for an invalid sequence of `n` bytes
+/// (like `String::from_utf8_lossy`) and `None` if the sequence is incomplete
+/// (only when not at EOF).
+fn decode(data: &[u8], eof: bool) -> Option<(Option<char>, usize)> {
+    if data[0] < 0x80 {
+        return Some((Some(data[0] as char), 1));
+    }
+    let prefix = &data[..data.len().min(4)];
+    let valid = match str::from_utf8(prefix) {
+        Ok(s) => s,
+        Err(e) if e.valid_up_to() > 0 => str::from_utf8(&prefix[..e.valid_up_to()]).unwrap(),
+        Err(e) => {
+            return match e.error_len() {
+                Some(n) => Some((None, n)),
+                None if eof => Some((None, prefix.len())),
+                None => None,
+            }
+        }
+    };
+    let c = valid.chars().next().unwrap();
+    Some((Some(c), c.len_utf8()))
+}
+
+/// Split the input in words separated by (Unicode) white space.
+/// Invalid UTF-8 sequences are not white space.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Words;
+
+impl Splitter for Words {
+    type Error = Error;
+    type TokenType = ();
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> SplitResult<'input, (), Error> {
+        // Skip leading spaces.
+        let mut start = 0;
+        while start < data.len() {
+            match decode(&data[start..], eof) {
+                Some((Some(c), n)) if c.is_whitespace() => start += n,
+                Some(_) => break,
+                None => return Ok((None, start)),
+            }
+        }
+        // Scan until space, marking end of word.
+        let mut i = start;
+        while i < data.len() {
+            match decode(&data[i..], eof) {
+                Some((Some(c), n)) if c.is_whitespace() => {
+                    return Ok((Some((&data[start..i], ())), i + n));
+                }
+                Some((_, n)) => i += n,
+                None => break,
+            }
+        }
+        // If we're at EOF, we have a final, non-empty, non-terminated word.
+        if eof && data.len() > start {
+            return Ok((Some((&data[start..], ())), data.len()));
+        }
+        // Request more data (after skipping the spaces).
+        Ok((None, start))
+    }
+}
+
+/// Split the input in UTF-8 encoded chars: each token is the encoding of
+/// its type.
+///
+/// An invalid sequence is returned as is with U+FFFD as its type
+/// (like `String::from_utf8_lossy`), unless `strict` is set.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Runes {
+    /// report invalid UTF-8 sequences as errors
+    /// (they are skipped in error recovery mode)
+    pub strict: bool,
+}
+
+impl Runes {
+    pub fn new() -> Runes {
+        Runes { strict: false }
+    }
+}
+
+impl Splitter for Runes {
+    type Error = Error;
+    type TokenType = char;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> SplitResult<'input, char, Error> {
+        if data.is_empty() {
+            return Ok((None, 0));
+        }
+        match decode(data, eof) {
+            Some((Some(c), n)) => Ok((Some((&data[..n], c)), n)),
+            Some((None, _)) if self.strict => {
+                Err(Error::new(ErrorKind::InvalidData, "invalid UTF-8 sequence"))
+            }
+            Some((None, n)) => Ok((Some((&data[..n], char::REPLACEMENT_CHARACTER)), n)),
+            // Request more data.
+            None => Ok((None, 0)),
+        }
+    }
+
+    /// Skip the invalid sequence.
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        if data.is_empty() {
+            return Some(0);
+        }
+        Some(decode(data, eof).map_or(0, |(_, n)| n))
+    }
+}
+
+/// Split the input in fields, like awk: by default, fields are separated by
+/// runs of blanks (space, tab or carriage return) and leading or trailing
+/// blanks are ignored. With a separator, each one separates two fields
+/// (which may be empty).
+///
+/// Lines are records: the `end_of_record` method tells when a field is the
+/// last one of its line. Empty lines have no field.
+/// The token type is the field number (first is 1).
+#[derive(Clone, Debug)]
+pub struct Fields {
+    /// fields separator (`None` for blanks)
+    sep: Option<u8>,
+    /// true when the most recent field is the last one of its line
+    eor: bool,
+    /// number of the most recent field in its line
+    field: usize,
+}
+
+impl Fields {
+    /// Fields separated by blanks
+    pub fn new() -> Fields {
+        Fields {
+            sep: None,
+            eor: true,
+            field: 0,
+        }
+    }
+
+    /// Fields separated by `sep` (like `awk -F`)
+    pub fn with_separator(sep: u8) -> Fields {
+        let mut fields = Fields::new();
+        fields.sep = Some(sep);
+        fields
+    }
+
+    pub fn end_of_record(&self) -> bool {
+        self.eor
+    }
+
+    /// Return the field `data[start..end]` and consume `amt` bytes.
+    fn field<'input>(
+        &mut self,
+        data: &'input [u8],
+        start: usize,
+        end: usize,
+        amt: usize,
+        eor: bool,
+    ) -> SplitResult<'input, usize, Error> {
+        self.field += 1;
+        let field = self.field;
+        self.eor = eor;
+        if eor {
+            self.field = 0;
+        }
+        Ok((Some((&data[start..end], field)), amt))
+    }
+
+    fn split_blanks<'input>(
+        &mut self,
+        data: &'input [u8],
+        eof: bool,
+    ) -> SplitResult<'input, usize, Error> {
+        // Skip leading blanks and empty lines.
+        let start = match data.iter().position(|b| !is_blank(*b)) {
+            Some(i) if data[i] == b'\n' => return Ok((None, i + 1)),
+            Some(i) => i,
+            None => return Ok((None, data.len())),
+        };
+        let end = match data[start..]
+            .iter()
+            .position(|b| is_blank(*b) || *b == b'\n')
+        {
+            Some(i) => start + i,
+            None if eof => return self.field(data, start, data.len(), data.len(), true),
+            // Request more data.
+            None => return Ok((None, start)),
+        };
+        // Is there another field on this line?
+        match data[end..].iter().position(|b| !is_blank(*b)) {
+            Some(i) if data[end + i] == b'\n' => self.field(data, start, end, end + i + 1, true),
+            Some(i) => self.field(data, start, end, end + i, false),
+            None if eof => self.field(data, start, end, data.len(), true),
+            // Request more data.
+            None => Ok((None, start)),
+        }
+    }
+
+    fn split_sep<'input>(
+        &mut self,
+        sep: u8,
+        data: &'input [u8],
+        eof: bool,
+    ) -> SplitResult<'input, usize, Error> {
+        if self.eor {
+            // Skip empty lines.
+            match data {
+                [b'\n', ..] => return Ok((None, 1)),
+                [b'\r', b'\n', ..] => return Ok((None, 2)),
+                [b'\r'] if !eof => return Ok((None, 0)),
+                // A lone `\r` at EOF is an empty line too.
+                [b'\r'] => return Ok((None, 1)),
+                [] => return Ok((None, 0)),
+                _ => {}
+            }
+        }
+        match memchr2(sep, b'\n', data) {
+            Some(i) if data[i] == sep => self.field(data, 0, i, i + 1, false),
+            Some(i) => {
+                let end = if i > 0 && data[i - 1] == b'\r' {
+                    i - 1
+                } else {
+                    i
+                };
+                self.field(data, 0, end, i + 1, true)
+            }
+            // If we're at EOF, we have a final field (empty after a separator).
+            None if eof => self.field(data, 0, data.len(), data.len(), true),
+            // Request more data.
+            None => Ok((None, 0)),
+        }
+    }
+}
+
+impl Default for Fields {
+    fn default() -> Fields {
+        Fields::new()
+    }
+}
+
+impl Splitter for Fields {
+    type Error = Error;
+    type TokenType = usize;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> SplitResult<'input, usize, Error> {
+        match self.sep {
+            Some(sep) => self.split_sep(sep, data, eof),
+            None => self.split_blanks(data, eof),
+        }
+    }
+}
+
+impl Resync for Fields {
+    fn resync(&self, data: &[u8]) -> Option<usize> {
+        memchr(b'\n', data).map(|i| i + 1)
+    }
+
+    fn end_of_record(&self) -> bool {
+        self.eor
+    }
+}
+
+fn is_blank(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\r'
+}
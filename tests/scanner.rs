@@ -0,0 +1,1230 @@
+//! Scanner tests, notably buffer management (growth, compaction and in-place
+//! unescaping across refills). They must also pass under Miri:
+//! `cargo +nightly miri test` (except those accessing the file system).
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use scan_rs::csv;
+use scan_rs::decompress;
+use scan_rs::sql::{self, TokenType};
+#[cfg(feature = "tokio")]
+use scan_rs::AsyncScanner;
+#[cfg(feature = "mmap")]
+use scan_rs::MmapScanner;
+#[cfg(feature = "codec")]
+use scan_rs::SplitterCodec;
+use scan_rs::{
+    scan_parallel, Checkpoint, ChunkScanner, ColumnMode, Fields, FollowScanner, Liner, LongLine,
+    Lookahead, MultiScanner, Resync, ReverseLiner, Runes, Scanner, SliceScanner, Span, Splitter,
+    Terminator, Words,
+};
+
+/// Reader returning at most `chunk` bytes per `read`.
+struct Chunked<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl<'a> Read for Chunked<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.chunk.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+type Tokens<S> = Result<Vec<(Vec<u8>, <S as Splitter>::TokenType)>, <S as Splitter>::Error>;
+
+fn scan_all<S: Splitter>(data: &[u8], chunk: usize, capacity: usize, splitter: S) -> Tokens<S> {
+    let reader = Chunked { data, chunk };
+    Scanner::with_capacity(reader, splitter, capacity)
+        .into_owned_iter()
+        .collect()
+}
+
+fn slice_scan_all<S: Splitter>(data: &[u8], splitter: S) -> Tokens<S> {
+    let mut data = data.to_vec();
+    let mut s = SliceScanner::new(&mut data, splitter);
+    let mut tokens = vec![];
+    while let Some((token, token_type)) = s.scan()? {
+        tokens.push((token.to_vec(), token_type));
+    }
+    Ok(tokens)
+}
+
+#[test]
+fn lines_with_buffer_growth() {
+    let data = b"first\r\nsecond line\n\nlast line without newline";
+    for &(chunk, capacity) in &[(1, 0), (1, 1), (3, 2), (5, 8), (64, 4096)] {
+        let lines: Vec<Vec<u8>> = scan_all(data, chunk, capacity, Liner::new())
+            .unwrap()
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                b"first".to_vec(),
+                b"second line".to_vec(),
+                b"".to_vec(),
+                b"last line without newline".to_vec(),
+            ]
+        );
+    }
+}
+
+#[test]
+fn lines_with_buffer_compaction() {
+    // Many short lines with a small buffer: the unprocessed data is
+    // repeatedly shifted to the beginning of the buffer.
+    let mut data = Vec::new();
+    for i in 0..50 {
+        data.extend_from_slice(format!("line {}\n", i).as_bytes());
+    }
+    let lines = scan_all(&data, 7, 16, Liner::new()).unwrap();
+    assert_eq!(lines.len(), 50);
+    for (i, (line, _)) in lines.iter().enumerate() {
+        assert_eq!(line, format!("line {}", i).as_bytes());
+    }
+}
+
+#[test]
+fn csv_unescape_across_refills() {
+    let data = b"a,\"b\"\"c\",\"d\ne\"\r\n\"\"\"\"\"\",f\n";
+    let expected = slice_scan_all(data, csv::Reader::new()).unwrap();
+    let fields: Vec<&[u8]> = expected.iter().map(|(f, _)| &f[..]).collect();
+    assert_eq!(fields, vec![&b"a"[..], b"b\"c", b"d\ne", b"\"\"", b"f"]);
+    for &(chunk, capacity) in &[(1, 1), (2, 3), (3, 4), (4, 16)] {
+        let fields = scan_all(data, chunk, capacity, csv::Reader::new()).unwrap();
+        let fields: Vec<&[u8]> = fields.iter().map(|(f, _)| &f[..]).collect();
+        assert_eq!(fields, vec![&b"a"[..], b"b\"c", b"d\ne", b"\"\"", b"f"]);
+    }
+}
+
+#[test]
+fn sql_unescape_across_refills() {
+    let data = b"SELECT 'it''s', \"a\"\"b\" FROM t; -- comment\n/* block */ x'0A'";
+    let expected = slice_scan_all(data, sql::Tokenizer::new()).unwrap();
+    assert_eq!(
+        expected,
+        vec![
+            (b"SELECT".to_vec(), TokenType::Select),
+            (b"it's".to_vec(), TokenType::StringLiteral),
+            (b",".to_vec(), TokenType::Comma),
+            (b"a\"b".to_vec(), TokenType::Id),
+            (b"FROM".to_vec(), TokenType::From),
+            (b"t".to_vec(), TokenType::Id),
+            (b";".to_vec(), TokenType::Semi),
+            (b"0A".to_vec(), TokenType::Blob),
+        ]
+    );
+    for &(chunk, capacity) in &[(1, 1), (2, 2), (5, 3), (7, 32)] {
+        let tokens = scan_all(data, chunk, capacity, sql::Tokenizer::new()).unwrap();
+        assert_eq!(tokens, expected);
+    }
+}
+
+#[test]
+fn error_position_across_refills() {
+    let data = b"SELECT 1\n  FROM t !";
+    for &(chunk, capacity) in &[(1, 1), (4, 4), (64, 4096)] {
+        match scan_all(data, chunk, capacity, sql::Tokenizer::new()) {
+            Err(sql::Error::ExpectedEqualsSign(pos)) => assert_eq!(pos, Some((2, 10, 18))),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    match slice_scan_all(data, sql::Tokenizer::new()) {
+        Err(sql::Error::ExpectedEqualsSign(pos)) => assert_eq!(pos, Some((2, 10, 18))),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+/// Splitter returning a constant instead of a slice of the input.
+struct Null;
+
+impl Splitter for Null {
+    type Error = io::Error;
+    type TokenType = ();
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        _eof: bool,
+    ) -> io::Result<(Option<(&'input [u8], ())>, usize)> {
+        if data.is_empty() {
+            return Ok((None, 0));
+        }
+        Ok((Some((b"NULL", ())), data.len()))
+    }
+}
+
+#[test]
+fn token_not_in_input() {
+    let mut data = b"x".to_vec();
+    for e in [
+        Scanner::new(&data[..], Null).scan().unwrap_err(),
+        SliceScanner::new(&mut data, Null).scan().unwrap_err(),
+    ] {
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn into_owned_iter() {
+    let lines: Vec<Vec<u8>> = Scanner::new(&b"a\nb\r\nc"[..], Liner::new())
+        .into_owned_iter()
+        .map(|r| r.map(|(line, _)| line))
+        .collect::<io::Result<_>>()
+        .unwrap();
+    assert_eq!(lines, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+    // the iteration stops after the first error
+    let mut iter = Scanner::new(&b"a,\"b"[..], csv::Reader::new()).into_owned_iter();
+    assert_eq!(iter.next().unwrap().unwrap().0, b"a");
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn token_too_long() {
+    let data = b"a,\"a long quoted field\"\n";
+    let reader = Chunked { data, chunk: 3 };
+    let mut s = Scanner::with_capacity(reader, csv::Reader::new(), 2);
+    s.set_max_token_size(8);
+    assert_eq!(s.scan().unwrap().map(|(f, _)| f), Some(&b"a"[..]));
+    match s.scan() {
+        Err(csv::Error::TokenTooLong(pos)) => assert_eq!(pos, Some((1, 3, 2))),
+        r => panic!(
+            "unexpected result: {:?}",
+            r.map(|t| t.map(|(f, _)| f.to_vec()))
+        ),
+    }
+}
+
+#[test]
+fn reuse_buffer() {
+    let mut s = Scanner::with_capacity(&b"a\nb\n"[..], Liner::new(), 1);
+    while s.scan().unwrap().is_some() {}
+    let (_, liner, buf) = s.into_parts();
+    let mut s = Scanner::with_buffer(&b"c\nd"[..], liner, buf);
+    assert_eq!(s.scan().unwrap().map(|(l, _)| l), Some(&b"c"[..]));
+    assert_eq!(s.scan().unwrap().map(|(l, _)| l), Some(&b"d"[..]));
+    assert_eq!(s.scan().unwrap().map(|(l, _)| l), None);
+}
+
+#[test]
+fn spans() {
+    let data = b"SELECT\n  'a''b' ;";
+    let reader = Chunked { data, chunk: 2 };
+    let mut s = Scanner::with_capacity(reader, sql::Tokenizer::new(), 1);
+    let mut spans = vec![];
+    while s.scan().unwrap().is_some() {
+        spans.push(s.span());
+    }
+    assert_eq!(
+        spans,
+        vec![
+            Span {
+                start_offset: 0,
+                end_offset: 6,
+                start_line: 1,
+                start_col: 1,
+                end_line: 1,
+                end_col: 7,
+            },
+            Span {
+                start_offset: 9,
+                end_offset: 15,
+                start_line: 2,
+                start_col: 3,
+                end_line: 2,
+                end_col: 9,
+            },
+            Span {
+                start_offset: 16,
+                end_offset: 17,
+                start_line: 2,
+                start_col: 10,
+                end_line: 2,
+                end_col: 11,
+            },
+        ]
+    );
+    assert_eq!(&data[9..15], b"'a''b'");
+}
+
+#[test]
+fn column_modes() {
+    // "é" is 2 bytes / 1 char / 1 UTF-16 unit, "😀" is 4 bytes / 1 char / 2 UTF-16 units
+    let data = "SELECT é, '😀'\t!".as_bytes();
+    let expected = &[
+        (ColumnMode::Bytes, 1, 19),
+        (ColumnMode::Chars, 1, 15),
+        (ColumnMode::Utf16, 1, 16),
+        (ColumnMode::Chars, 8, 17),
+    ];
+    for &(column_mode, tab_width, column) in expected {
+        let mut s = Scanner::new(data, sql::Tokenizer::new());
+        s.set_column_mode(column_mode);
+        s.set_tab_width(tab_width);
+        loop {
+            match s.scan() {
+                Err(sql::Error::ExpectedEqualsSign(pos)) => {
+                    assert_eq!(pos, Some((1, column, 18)));
+                    break;
+                }
+                Ok(Some(_)) => {}
+                r => panic!("unexpected result: {:?}", r),
+            }
+        }
+    }
+}
+
+#[test]
+fn lookahead() {
+    let data = b"SELECT a FROM t;";
+    let reader = Chunked { data, chunk: 3 };
+    let mut s = Lookahead::new(Scanner::with_capacity(reader, sql::Tokenizer::new(), 1));
+    assert_eq!(
+        s.peek().unwrap(),
+        Some((&b"SELECT"[..], &TokenType::Select))
+    );
+    assert_eq!(
+        s.peek_nth(2).unwrap(),
+        Some((&b"FROM"[..], &TokenType::From))
+    );
+    assert_eq!(s.scan().unwrap(), Some((&b"SELECT"[..], TokenType::Select)));
+    assert_eq!(s.scan().unwrap(), Some((&b"a"[..], TokenType::Id)));
+    assert!(s.unscan());
+    assert!(!s.unscan());
+    assert_eq!(s.scan().unwrap(), Some((&b"a"[..], TokenType::Id)));
+    assert_eq!(s.scan().unwrap(), Some((&b"FROM"[..], TokenType::From)));
+    // no lookahead: borrowed from the scanner buffer
+    assert_eq!(s.scan().unwrap(), Some((&b"t"[..], TokenType::Id)));
+    assert_eq!(s.span().start_offset, 14);
+    // the last token is saved before peeking further
+    assert_eq!(s.peek_nth(5).unwrap(), None);
+    assert!(s.unscan());
+    assert_eq!(s.scan().unwrap(), Some((&b"t"[..], TokenType::Id)));
+    assert_eq!(s.scan().unwrap(), Some((&b";"[..], TokenType::Semi)));
+    assert_eq!(s.scan().unwrap(), None);
+}
+
+#[test]
+fn checkpoint_and_resume() {
+    let data = b"a,b\n\"c\nd\",e\nf,g\n";
+    let mut s = Scanner::with_capacity(&data[..], csv::Reader::new(), 1);
+    assert_eq!(s.scan().unwrap().map(|(f, _)| f), Some(&b"a"[..]));
+    assert_eq!(s.scan().unwrap().map(|(f, _)| f), Some(&b"b"[..]));
+    assert_eq!(s.scan().unwrap().map(|(f, _)| f), Some(&b"c\nd"[..]));
+    let checkpoint = s.checkpoint();
+    assert_eq!(
+        (checkpoint.offset, checkpoint.line, checkpoint.column),
+        (10, 3, 4)
+    );
+    assert!(!checkpoint.splitter.end_of_record());
+    let expected: Vec<_> = s.into_owned_iter().map(|t| t.unwrap().0).collect();
+    assert_eq!(expected, vec![b"e".to_vec(), b"f".to_vec(), b"g".to_vec()]);
+
+    let mut s = Scanner::resume(io::Cursor::new(&data[..]), checkpoint).unwrap();
+    assert_eq!(s.scan().unwrap().map(|(f, _)| f), Some(&b"e"[..]));
+    assert!(s.splitter().end_of_record());
+    assert_eq!(s.span().start_offset, 10);
+    assert_eq!((s.line(), s.column()), (4, 1));
+    let rest: Vec<_> = s.into_owned_iter().map(|t| t.unwrap().0).collect();
+    assert_eq!(rest, vec![b"f".to_vec(), b"g".to_vec()]);
+
+    // persisted at a record boundary and rebuilt (like in another process)
+    let mut s = Scanner::new(&data[..], csv::Reader::custom(b',', true));
+    s.scan().unwrap();
+    s.scan().unwrap();
+    let saved = {
+        let checkpoint = s.checkpoint();
+        let reader = &checkpoint.splitter;
+        assert!(reader.end_of_record());
+        format!(
+            "{} {} {} {} {}",
+            checkpoint.offset,
+            checkpoint.line,
+            checkpoint.column,
+            reader.separator(),
+            reader.quoted()
+        )
+    };
+    let fields: Vec<&str> = saved.split(' ').collect();
+    let checkpoint = Checkpoint {
+        offset: fields[0].parse().unwrap(),
+        line: fields[1].parse().unwrap(),
+        column: fields[2].parse().unwrap(),
+        splitter: csv::Reader::custom(fields[3].parse().unwrap(), fields[4].parse().unwrap()),
+    };
+    let s = Scanner::resume(io::Cursor::new(&data[..]), checkpoint).unwrap();
+    let rest: Vec<_> = s.into_owned_iter().map(|t| t.unwrap().0).collect();
+    assert_eq!(
+        rest,
+        vec![
+            b"c\nd".to_vec(),
+            b"e".to_vec(),
+            b"f".to_vec(),
+            b"g".to_vec()
+        ]
+    );
+}
+
+/// Temporary file removed on drop.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, data: &[u8]) -> TempFile {
+        let path = env::temp_dir().join(format!("scan-rs-{}-{}", process::id(), name));
+        fs::write(&path, data).unwrap();
+        TempFile(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn chunk_tokens<S: Resync>(chunk: &mut ChunkScanner<S>) -> Vec<Vec<u8>> {
+    let mut tokens = vec![];
+    while let Ok(Some((token, _))) = chunk.scan() {
+        tokens.push(token.to_vec());
+    }
+    tokens
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn parallel_lines() {
+    let mut data = Vec::new();
+    for i in 0..100 {
+        data.extend_from_slice(format!("line {}\r\n", i).as_bytes());
+    }
+    data.extend_from_slice(b"last");
+    let file = TempFile::new("parallel_lines", &data);
+    let expected: Vec<_> = slice_scan_all(&data, Liner::new())
+        .unwrap()
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect();
+    for &chunks in &[1, 2, 3, 7, 64, 1000] {
+        let lines: Vec<_> = scan_parallel(&file.0, chunks, Liner::new(), chunk_tokens)
+            .unwrap()
+            .concat();
+        assert_eq!(lines, expected, "{} chunks", chunks);
+    }
+
+    // the panic of the callback is propagated as is
+    let panic = std::panic::catch_unwind(|| {
+        scan_parallel(&file.0, 2, Liner::new(), |_| panic!("callback panic"))
+    })
+    .unwrap_err();
+    assert_eq!(panic.downcast_ref::<&str>(), Some(&"callback panic"));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn parallel_csv() {
+    // quoted newlines and quotes make the speculation fail for some chunks
+    let mut data = Vec::new();
+    for i in 0..50 {
+        data.extend_from_slice(format!("{},\"a\nb\"\"\n\"\"c\",d\n\"\n,\"\n", i).as_bytes());
+    }
+    let file = TempFile::new("parallel_csv", &data);
+    let expected: Vec<_> = slice_scan_all(&data, csv::Reader::new())
+        .unwrap()
+        .into_iter()
+        .map(|(field, _)| field)
+        .collect();
+    assert_eq!(expected.len(), 200);
+    for &chunks in &[1, 2, 3, 5, 16, 100, 1000] {
+        let fields: Vec<_> = scan_parallel(&file.0, chunks, csv::Reader::new(), chunk_tokens)
+            .unwrap()
+            .concat();
+        assert_eq!(fields, expected, "{} chunks", chunks);
+    }
+
+    // an error (unescaped quote) at the start: the speculation must still be
+    // checked
+    let mut data = b"x,\"y\"z\n".to_vec();
+    for i in 0..50 {
+        data.extend_from_slice(format!("{},\"a\nb\nc\",d\n", i).as_bytes());
+    }
+    let file = TempFile::new("parallel_csv_error", &data);
+    let mut s = Scanner::new(&data[..], csv::Reader::new());
+    s.set_error_recovery(true);
+    let mut expected = vec![];
+    while let Some((field, _)) = s.scan().unwrap() {
+        let field = field.to_vec();
+        expected.push((s.span().start_offset, field));
+    }
+    for &chunks in &[2, 3, 5, 16, 100] {
+        let fields = scan_parallel(&file.0, chunks, csv::Reader::new(), |chunk| {
+            let mut fields = vec![];
+            while let Ok(Some((field, _))) = chunk.scan() {
+                let field = field.to_vec();
+                fields.push((chunk.scanner().span().start_offset, field));
+            }
+            fields
+        })
+        .unwrap()
+        .concat();
+        // no garbage and up to the last field
+        let mut rest = expected.iter();
+        for field in &fields {
+            assert!(rest.any(|f| f == field), "{} chunks: {:?}", chunks, field);
+        }
+        assert_eq!(fields.last(), expected.last(), "{} chunks", chunks);
+    }
+}
+
+#[test]
+fn error_recovery() {
+    let data = b"SELECT ! 1;\n\\bad; x'0g' 2\n'unterminated";
+    for &(chunk, capacity) in &[(1, 1), (64, 4096)] {
+        let reader = Chunked { data, chunk };
+        let mut s = Scanner::with_capacity(reader, sql::Tokenizer::new(), capacity);
+        s.set_error_recovery(true);
+        let mut tokens = s.into_owned_iter();
+        let token_types: Vec<_> = tokens.by_ref().map(|t| t.unwrap().1).collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Select,
+                TokenType::Integer,
+                TokenType::Semi,
+                TokenType::Semi,
+                TokenType::Integer,
+            ]
+        );
+        let mut s = tokens.into_inner();
+        let errors = s.take_errors();
+        assert_eq!(errors.len(), 4);
+        match (&errors[0], &errors[1], &errors[2], &errors[3]) {
+            (
+                sql::Error::ExpectedEqualsSign(Some((1, 8, 7))),
+                sql::Error::UnrecognizedToken(Some((2, 1, 12))),
+                sql::Error::MalformedBlobLiteral(Some((2, 7, 18))),
+                sql::Error::UnterminatedLiteral(Some((3, 1, 26))),
+            ) => {}
+            r => panic!("unexpected errors: {:?}", r),
+        }
+    }
+
+    let data = b"a,\"b\"c,d\ne,f\n";
+    let mut s = Scanner::new(&data[..], csv::Reader::new());
+    s.set_error_recovery(true);
+    let mut fields = s.into_owned_iter();
+    let values: Vec<_> = fields.by_ref().map(|t| t.unwrap().0).collect();
+    assert_eq!(values, vec![b"a".to_vec(), b"e".to_vec(), b"f".to_vec()]);
+    match fields.into_inner().errors() {
+        [csv::Error::UnescapedQuote { pos, .. }] => assert_eq!(*pos, Some((1, 3, 2))),
+        r => panic!("unexpected errors: {:?}", r),
+    }
+    // the newline in the quoted field does not end the record
+    let data = b"\"a\nb\"x,c\nd\n";
+    for &(chunk, capacity) in &[(1, 1), (64, 4096)] {
+        let reader = Chunked { data, chunk };
+        let mut s = Scanner::with_capacity(reader, csv::Reader::new(), capacity);
+        s.set_error_recovery(true);
+        let mut fields = s.into_owned_iter();
+        let values: Vec<_> = fields.by_ref().map(|t| t.unwrap().0).collect();
+        assert_eq!(values, vec![b"d".to_vec()]);
+        assert_eq!(fields.into_inner().errors().len(), 1);
+    }
+
+    // `recover` is called once per error when the data is available
+    let mut s = Scanner::new(&data[..], Recovers(csv::Reader::new(), 0));
+    s.set_error_recovery(true);
+    while s.scan().unwrap().is_some() {}
+    assert_eq!(s.splitter().1, 1);
+}
+
+/// Splitter counting the calls to `recover`.
+struct Recovers<S>(S, usize);
+
+impl<S: Splitter> Splitter for Recovers<S> {
+    type Error = S::Error;
+    type TokenType = S::TokenType;
+
+    fn split<'input>(
+        &mut self,
+        data: &'input mut [u8],
+        eof: bool,
+    ) -> Result<(Option<(&'input [u8], S::TokenType)>, usize), S::Error> {
+        self.0.split(data, eof)
+    }
+
+    fn recover(&mut self, data: &[u8], eof: bool) -> Option<usize> {
+        self.1 += 1;
+        self.0.recover(data, eof)
+    }
+}
+
+#[test]
+fn splitter_adapters() {
+    let data = b"SELECT 1; SELECT 2;";
+    let tokens = slice_scan_all(
+        data,
+        sql::Tokenizer::new().skip_types(vec![TokenType::Semi]),
+    );
+    let tokens: Vec<_> = tokens.unwrap().into_iter().map(|(t, _)| t).collect();
+    assert_eq!(tokens, vec![&b"SELECT"[..], b"1", b"SELECT", b"2"]);
+
+    let splitter = sql::Tokenizer::new().filter_tokens(|token, _| token != b"1");
+    let tokens = scan_all(data, 2, 1, splitter).unwrap();
+    assert_eq!(tokens.len(), 5);
+
+    let data = b"a,\"b\",\"c\"\"\"\n";
+    let splitter = csv::Reader::new().map_token(|field, field_type| match field_type {
+        csv::FieldType::Unquoted => field.len(),
+        _ => 0,
+    });
+    let fields = scan_all(data, 3, 2, splitter).unwrap();
+    assert_eq!(
+        fields,
+        vec![(b"a".to_vec(), 1), (b"b".to_vec(), 0), (b"c\"".to_vec(), 0)]
+    );
+
+    // errors and their position come from the underlying splitter
+    let splitter = sql::Tokenizer::new().skip_types(vec![TokenType::Select]);
+    match scan_all(b"SELECT\n  !", 1, 1, splitter) {
+        Err(sql::Error::ExpectedEqualsSign(pos)) => assert_eq!(pos, Some((2, 3, 9))),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn multi_sources() {
+    let sources = vec![
+        ("a.sql", &b"SELECT 1;\n"[..]),
+        ("empty.sql", &b""[..]),
+        ("b.sql", &b"SELECT\n  !"[..]),
+    ];
+    let mut s = MultiScanner::new(sources, sql::Tokenizer::new());
+    let mut tokens = vec![];
+    let err = loop {
+        match s.scan() {
+            Ok(Some((token, _))) => {
+                let token = token.to_vec();
+                tokens.push((s.name().to_owned(), token));
+            }
+            Ok(None) => panic!("missing error"),
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(
+        tokens,
+        vec![
+            ("a.sql".to_owned(), b"SELECT".to_vec()),
+            ("a.sql".to_owned(), b"1".to_vec()),
+            ("a.sql".to_owned(), b";".to_vec()),
+            ("b.sql".to_owned(), b"SELECT".to_vec()),
+        ]
+    );
+    assert_eq!((err.name.as_str(), err.line, err.column), ("b.sql", 2, 3));
+    match err.error {
+        sql::Error::ExpectedEqualsSign(pos) => assert_eq!(pos, Some((2, 3, 9))),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    // the splitter state (a line counter here) is reset for each source
+    // unless it is carried over
+    let sources = vec![("1", &b"a\nb\n"[..]), ("2", &b"c\n"[..])];
+    let mut numbers = vec![];
+    for &carry_state in &[false, true] {
+        let mut n = 0;
+        let splitter = Liner::new().map_token(move |_, _| {
+            n += 1;
+            n
+        });
+        let mut s = MultiScanner::new(sources.clone(), splitter);
+        s.set_carry_state(carry_state);
+        while let Some((_, n)) = s.scan().unwrap() {
+            numbers.push(n);
+        }
+    }
+    assert_eq!(numbers, vec![1, 2, 1, 1, 2, 3]);
+}
+
+#[test]
+fn multi_sources_lazy() {
+    // sources are opened one at a time and an erroneous one can be skipped
+    let opened = Cell::new(0);
+    let sources = vec![
+        ("a.sql", &b"SELECT 1;"[..]),
+        ("b.sql", &b"SELECT\n  !"[..]),
+        ("c.sql", &b"SELECT 2;"[..]),
+    ]
+    .into_iter()
+    .inspect(|_| opened.set(opened.get() + 1));
+    let mut s = MultiScanner::new(sources, sql::Tokenizer::new());
+    assert_eq!(opened.get(), 1);
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    loop {
+        match s.scan() {
+            Ok(Some((token, _))) => {
+                let token = token.to_vec();
+                tokens.push((s.name().to_owned(), token));
+            }
+            Ok(None) => break,
+            Err(err) => {
+                errors.push(err.name);
+                assert_eq!(opened.get(), 2);
+                if !s.next_source() {
+                    break;
+                }
+            }
+        }
+    }
+    assert_eq!(opened.get(), 3);
+    assert_eq!(errors, vec!["b.sql".to_owned()]);
+    let names: Vec<_> = tokens.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["a.sql", "a.sql", "a.sql", "b.sql", "c.sql", "c.sql", "c.sql"]
+    );
+
+    // settings are kept from one source to the next one
+    let sources = vec![("x", &b"a\xffb"[..]), ("y", &b"\xfec"[..])];
+    let mut s = MultiScanner::new(sources, Runes { strict: true });
+    s.set_error_recovery(true);
+    let mut runes = vec![];
+    while let Some((_, c)) = s.scan().unwrap() {
+        runes.push(c);
+    }
+    assert_eq!(runes, vec!['a', 'b', 'c']);
+    let errors: Vec<_> = s.take_errors().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(errors, vec!["x".to_owned(), "y".to_owned()]);
+
+    let long = vec![b'a'; 5000];
+    let sources = vec![("x", &b"ab\n"[..]), ("y", &long[..])];
+    let mut s = MultiScanner::new(sources, Liner::new());
+    s.set_max_token_size(100);
+    assert_eq!(s.scan().unwrap().unwrap().0, b"ab");
+    let err = s.scan().unwrap_err();
+    assert_eq!(err.name, "y");
+    assert_eq!(err.error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn decompress_plain() {
+    let inner = decompress(&b"a\nb"[..]).unwrap();
+    let lines = Scanner::new(inner, Liner::new()).into_owned_iter();
+    let lines: Vec<_> = lines.map(|l| l.unwrap().0).collect();
+    assert_eq!(lines, vec![b"a".to_vec(), b"b".to_vec()]);
+    // shorter than the magic bytes
+    let inner = decompress(&b"a"[..]).unwrap();
+    assert_eq!(
+        Scanner::new(inner, Liner::new()).into_owned_iter().count(),
+        1
+    );
+    // not a bzip2 block size
+    for data in &[&b"BZh"[..], b"BZh0", b"BZhello"] {
+        let inner = decompress(*data).unwrap();
+        let lines = Scanner::new(inner, Liner::new()).into_owned_iter();
+        let lines: Vec<_> = lines.map(|l| l.unwrap().0).collect();
+        assert_eq!(lines, vec![data.to_vec()]);
+    }
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn decompress_disabled() {
+    match decompress(&b"\x1f\x8b\x08\x00"[..]) {
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::Unsupported),
+        Ok(_) => panic!("gzip is not enabled"),
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn open_gzip() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"SELECT 1;\nSELECT\n  !").unwrap();
+    let file = TempFile::new("open_gzip.sql.gz", &encoder.finish().unwrap());
+    let mut s = Scanner::open(&file.0, sql::Tokenizer::new()).unwrap();
+    let mut count = 0;
+    loop {
+        match s.scan() {
+            Ok(Some(_)) => count += 1,
+            // position in the decompressed data
+            Err(sql::Error::ExpectedEqualsSign(pos)) => {
+                assert_eq!(pos, Some((3, 3, 19)));
+                break;
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    assert_eq!(count, 4);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn open_zstd() {
+    let data = zstd::encode_all(&b"a,b\nc,d\n"[..], 0).unwrap();
+    let file = TempFile::new("open_zstd.csv.zst", &data);
+    let s = Scanner::open(&file.0, csv::Reader::new()).unwrap();
+    assert_eq!(s.into_owned_iter().count(), 4);
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn open_bzip2() {
+    use bzip2::write::BzEncoder;
+    use bzip2::Compression;
+    use std::io::Write;
+
+    let mut encoder = BzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"a\nb\nc").unwrap();
+    let file = TempFile::new("open_bzip2.txt.bz2", &encoder.finish().unwrap());
+    let s = Scanner::open(&file.0, Liner::new()).unwrap();
+    assert_eq!(s.into_owned_iter().count(), 3);
+}
+
+#[test]
+fn liner_options() {
+    let lines = |liner: Liner, data: &[u8]| -> Vec<(Vec<u8>, Terminator)> {
+        let expected = slice_scan_all(data, liner.clone()).unwrap();
+        // the same with a terminator split between two reads
+        assert_eq!(scan_all(data, 1, 1, liner).unwrap(), expected);
+        expected
+    };
+    let data = b"a\nb\r\nc\rd\r";
+    assert_eq!(
+        lines(Liner::new(), data),
+        vec![
+            (b"a".to_vec(), Terminator::Lf),
+            (b"b".to_vec(), Terminator::CrLf),
+            (b"c\rd".to_vec(), Terminator::Eof),
+        ]
+    );
+    let mut liner = Liner::new();
+    liner.universal_newlines = true;
+    assert_eq!(
+        lines(liner.clone(), data),
+        vec![
+            (b"a".to_vec(), Terminator::Lf),
+            (b"b".to_vec(), Terminator::CrLf),
+            (b"c".to_vec(), Terminator::Cr),
+            (b"d".to_vec(), Terminator::Cr),
+        ]
+    );
+    liner.keep_terminator = true;
+    assert_eq!(
+        lines(liner, data),
+        vec![
+            (b"a\n".to_vec(), Terminator::Lf),
+            (b"b\r\n".to_vec(), Terminator::CrLf),
+            (b"c\r".to_vec(), Terminator::Cr),
+            (b"d\r".to_vec(), Terminator::Cr),
+        ]
+    );
+    assert_eq!(
+        lines(Liner::with_delimiter(b"\0"), b"a b\0\nc\0d"),
+        vec![
+            (b"a b".to_vec(), Terminator::Delimiter),
+            (b"\nc".to_vec(), Terminator::Delimiter),
+            (b"d".to_vec(), Terminator::Eof),
+        ]
+    );
+    let mut liner = Liner::with_delimiter(b"--");
+    liner.keep_terminator = true;
+    assert_eq!(
+        lines(liner, b"a-b--c--"),
+        vec![
+            (b"a-b--".to_vec(), Terminator::Delimiter),
+            (b"c--".to_vec(), Terminator::Delimiter),
+        ]
+    );
+}
+
+#[test]
+fn liner_long_lines() {
+    let mut data = b"short\n".to_vec();
+    data.resize(data.len() + 1000, b'x');
+    data.extend_from_slice(b"\r\n12345678\r\nlast line");
+    let scan = |long_line: LongLine| {
+        let mut liner = Liner::new();
+        liner.max_line_length = 8;
+        liner.long_line = long_line;
+        let reader = Chunked {
+            data: &data,
+            chunk: 3,
+        };
+        let mut s = Scanner::with_capacity(reader, liner, 1).into_owned_iter();
+        let lines: Vec<_> = s.by_ref().collect();
+        let (_, _, buf) = s.into_inner().into_parts();
+        // the buffer does not grow with the long line
+        assert!(buf.len() <= 16, "buffer: {}", buf.len());
+        lines
+    };
+    let lines: Vec<_> = scan(LongLine::Truncate)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (b"short".to_vec(), Terminator::Lf),
+            (b"xxxxxxxx".to_vec(), Terminator::Truncated),
+            (b"12345678".to_vec(), Terminator::CrLf),
+            (b"last lin".to_vec(), Terminator::Truncated),
+        ]
+    );
+    let lines: Vec<_> = scan(LongLine::Skip)
+        .into_iter()
+        .map(|l| l.unwrap().0)
+        .collect();
+    assert_eq!(lines, vec![b"short".to_vec(), b"12345678".to_vec()]);
+    let lines = scan(LongLine::Error);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[1].as_ref().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    // the rest of a long line is skipped in error recovery mode
+    let mut liner = Liner::new();
+    liner.max_line_length = 8;
+    let mut s = Scanner::with_capacity(&data[..], liner, 1);
+    s.set_error_recovery(true);
+    let mut lines = s.into_owned_iter();
+    let count = lines.by_ref().map(Result::unwrap).count();
+    assert_eq!((count, lines.into_inner().errors().len()), (2, 2));
+}
+
+#[test]
+fn reverse_lines() {
+    let inputs: &[&[u8]] = &[
+        b"",
+        b"\n",
+        b"\r",
+        b"a",
+        b"a\r",
+        b"a\n",
+        b"first\r\nsecond line\n\nlast line without newline",
+        b"\n\nlonger line\r\n\r\nx\n",
+        b"old\rmac\r\r\nmixed\n\r",
+        b"a b\0\nc\0\0d\0",
+        b"a-b--c-----d-",
+    ];
+    let mut universal = Liner::new();
+    universal.universal_newlines = true;
+    let mut liners = vec![Liner::new(), universal, Liner::with_delimiter(b"\0")];
+    for liner in liners.clone() {
+        let mut liner = liner;
+        liner.keep_terminator = true;
+        liners.push(liner);
+    }
+    // "-+" does not overlap itself
+    liners.push(Liner::with_delimiter(b"-+"));
+    liners.push(Liner::with_delimiter(b"\r\n"));
+    for liner in &liners {
+        for data in inputs {
+            let mut expected = slice_scan_all(data, liner.clone()).unwrap();
+            expected.reverse();
+            for &capacity in &[0, 1, 3, 4096] {
+                let mut s =
+                    ReverseLiner::with_capacity(io::Cursor::new(data), liner.clone(), capacity)
+                        .unwrap();
+                let mut lines = vec![];
+                let mut offsets = vec![];
+                while let Some((line, terminator)) = s.scan().unwrap() {
+                    lines.push((line.to_vec(), terminator));
+                    offsets.push(s.offset() as usize);
+                }
+                assert_eq!(
+                    lines, expected,
+                    "{:?} with {:?} and capacity {}",
+                    data, liner, capacity
+                );
+                for (offset, (line, _)) in offsets.iter().zip(&lines) {
+                    assert!(data[*offset..].starts_with(line));
+                }
+            }
+        }
+    }
+    let data = b"1\n2\n3\n4\n";
+    let mut s = ReverseLiner::new(io::Cursor::new(&data[..]), Liner::new()).unwrap();
+    assert_eq!(s.tail(2).unwrap(), vec![b"3".to_vec(), b"4".to_vec()]);
+    assert_eq!(s.offset(), 4);
+    assert_eq!(s.tail(5).unwrap(), vec![b"1".to_vec(), b"2".to_vec()]);
+
+    // unsupported configurations
+    let mut liner = Liner::new();
+    liner.max_line_length = 8;
+    for liner in [
+        liner,
+        Liner::with_delimiter(b"--"),
+        Liner::with_delimiter(b"aba"),
+    ] {
+        match ReverseLiner::new(io::Cursor::new(&data[..]), liner) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("unsupported configuration"),
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn follow_file() {
+    fn append(path: &PathBuf, data: &[u8]) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(data).unwrap();
+    }
+    fn lines(s: &mut FollowScanner<Liner>) -> Vec<Vec<u8>> {
+        let mut lines = vec![];
+        while let Some((line, _)) = s.scan().unwrap() {
+            lines.push(line.to_vec());
+        }
+        lines
+    }
+    let file = TempFile::new("follow", b"a\nb");
+    let mut s = FollowScanner::open(&file.0, Liner::new()).unwrap();
+    s.set_timeout(Some(Duration::ZERO));
+    // the partial last line is kept until it is terminated
+    assert_eq!(lines(&mut s), vec![b"a".to_vec()]);
+    append(&file.0, b"c\nd\n");
+    assert_eq!(lines(&mut s), vec![b"bc".to_vec(), b"d".to_vec()]);
+    assert_eq!(s.offset(), 7);
+    // truncation
+    fs::write(&file.0, b"x\n").unwrap();
+    assert_eq!(lines(&mut s), vec![b"x".to_vec()]);
+    assert_eq!((s.line(), s.offset()), (2, 2));
+    // rotation
+    if cfg!(unix) {
+        let rotated = TempFile(file.0.with_extension("1"));
+        fs::rename(&file.0, &rotated.0).unwrap();
+        append(&rotated.0, b"y");
+        fs::write(&file.0, b"z\n").unwrap();
+        assert_eq!(lines(&mut s), vec![b"y".to_vec(), b"z".to_vec()]);
+        assert_eq!(s.line(), 2);
+    }
+}
+
+#[test]
+fn words_runes_fields() {
+    let data = "\u{3000} héllo\u{a0}wörld\t\n\u{2028}x\u{85}\u{ff}y ".as_bytes();
+    for &(chunk, capacity) in &[(1, 0), (2, 1), (64, 4096)] {
+        let words: Vec<_> = scan_all(data, chunk, capacity, Words)
+            .unwrap()
+            .into_iter()
+            .map(|(word, _)| String::from_utf8(word).unwrap())
+            .collect();
+        assert_eq!(words, ["héllo", "wörld", "x", "\u{ff}y"]);
+    }
+
+    let data = b"a\xc3\xa9\xff\xe2\x82";
+    for &chunk in &[1, 64] {
+        let runes: Vec<_> = scan_all(data, chunk, 0, Runes::new())
+            .unwrap()
+            .into_iter()
+            .map(|(rune, c)| (rune.len(), c))
+            .collect();
+        assert_eq!(
+            runes,
+            [(1, 'a'), (2, 'é'), (1, '\u{fffd}'), (2, '\u{fffd}')]
+        );
+    }
+    let mut strict = Runes::new();
+    strict.strict = true;
+    let mut s = Scanner::new(&data[..], strict);
+    assert_eq!(s.scan().unwrap(), Some((&b"a"[..], 'a')));
+    assert_eq!(s.scan().unwrap(), Some((&data[1..3], 'é')));
+    assert!(s.scan().is_err());
+    let mut s = Scanner::new(&data[..], strict);
+    s.set_error_recovery(true);
+    assert_eq!(s.into_owned_iter().count(), 2);
+
+    fn fields(data: &[u8], splitter: Fields) -> Vec<(String, usize, bool)> {
+        let mut s = Scanner::with_capacity(Chunked { data, chunk: 1 }, splitter, 1);
+        let mut fields = vec![];
+        while let Some((field, n)) = s.scan().unwrap() {
+            let field = String::from_utf8(field.to_vec()).unwrap();
+            fields.push((field, n, s.splitter().end_of_record()));
+        }
+        fields
+    }
+    let data = b"  a \t bc\r\n\n \r\nd  \ne";
+    let expected = [
+        ("a", 1, false),
+        ("bc", 2, true),
+        ("d", 1, true),
+        ("e", 1, true),
+    ];
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|&(field, n, eor)| (field.to_owned(), n, eor))
+        .collect();
+    assert_eq!(fields(data, Fields::new()), expected);
+    let data = b"a::b\r\n\n:\nc:";
+    let expected = [
+        ("a", 1, false),
+        ("", 2, false),
+        ("b", 3, true),
+        ("", 1, false),
+        ("", 2, true),
+        ("c", 1, false),
+        ("", 2, true),
+    ];
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|&(field, n, eor)| (field.to_owned(), n, eor))
+        .collect();
+    assert_eq!(fields(data, Fields::with_separator(b':')), expected);
+    // a lone `\r` at EOF is an empty line
+    assert_eq!(
+        fields(b"a\n\r", Fields::with_separator(b':')),
+        vec![("a".to_owned(), 1, true)]
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+#[cfg_attr(miri, ignore)] // file system access
+fn mmap_csv() {
+    let data = b"a,\"b\"\"c\"\r\n\"d\"\"\"\"\",e";
+    let file = TempFile::new("mmap_csv", data);
+    let mut s = MmapScanner::open(&fs::File::open(&file.0).unwrap(), csv::Reader::new()).unwrap();
+    let mut fields = vec![];
+    while let Some((field, field_type)) = s.scan().unwrap() {
+        fields.push((field.to_vec(), field_type));
+    }
+    assert_eq!(fields, slice_scan_all(data, csv::Reader::new()).unwrap());
+    assert_eq!(fields[1], (b"b\"c".to_vec(), csv::FieldType::Escaped));
+    // the quotes are unescaped in the private mapping only
+    assert_eq!(fs::read(&file.0).unwrap(), &data[..]);
+}
+
+/// Scan `data` written to a duplex stream of 3 bytes.
+#[cfg(feature = "tokio")]
+async fn async_scan_all<S: Splitter>(
+    data: &'static [u8],
+    capacity: usize,
+    max_token_size: usize,
+    splitter: S,
+) -> Tokens<S> {
+    use tokio::io::AsyncWriteExt;
+    let (mut client, server) = tokio::io::duplex(3);
+    tokio::spawn(async move {
+        // the scanner may stop reading first
+        let _ = client.write_all(data).await;
+    });
+    let mut s = AsyncScanner::with_capacity(server, splitter, capacity);
+    s.set_max_token_size(max_token_size);
+    let mut tokens = vec![];
+    while let Some((token, token_type)) = s.scan().await? {
+        tokens.push((token.to_vec(), token_type));
+    }
+    Ok(tokens)
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_duplex() {
+    let data = b"a,\"b\"\"c\",\"d\ne\"\r\n\"\"\"\"\"\",f\n";
+    for &capacity in &[1, 4, 4096] {
+        let fields = async_scan_all(data, capacity, usize::MAX, csv::Reader::new())
+            .await
+            .unwrap();
+        assert_eq!(fields, slice_scan_all(data, csv::Reader::new()).unwrap());
+    }
+
+    let data = b"a,\"a long quoted field\"\n";
+    match async_scan_all(data, 2, 8, csv::Reader::new()).await {
+        Err(csv::Error::TokenTooLong(pos)) => assert_eq!(pos, Some((1, 3, 2))),
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    let data = b"SELECT 1\n  FROM t !";
+    match async_scan_all(data, 4, usize::MAX, sql::Tokenizer::new()).await {
+        Err(sql::Error::ExpectedEqualsSign(pos)) => assert_eq!(pos, Some((2, 10, 18))),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[cfg(feature = "codec")]
+#[tokio::test]
+async fn codec() {
+    use bytes::BytesMut;
+    use futures_util::StreamExt;
+    use tokio_util::codec::{Decoder, FramedRead};
+
+    // the last unterminated line is only returned at EOF
+    let mut codec = SplitterCodec::new(Liner::new());
+    let mut src = BytesMut::from(&b"a\nb"[..]);
+    assert_eq!(codec.decode(&mut src).unwrap().unwrap().0, &b"a"[..]);
+    assert!(codec.decode(&mut src).unwrap().is_none());
+    assert_eq!(codec.decode_eof(&mut src).unwrap().unwrap().0, &b"b"[..]);
+    assert!(codec.decode_eof(&mut src).unwrap().is_none());
+
+    let data = b"a,\"b\"\"c\",\"d\ne\"\r\n\"\"\"\"\"\",f";
+    let fields: Vec<_> = FramedRead::new(&data[..], SplitterCodec::new(csv::Reader::new()))
+        .map(|r| r.map(|(field, field_type)| (field.to_vec(), field_type)))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(fields, slice_scan_all(data, csv::Reader::new()).unwrap());
+    assert_eq!(fields[1], (b"b\"c".to_vec(), csv::FieldType::Escaped));
+
+    let mut codec = SplitterCodec::new(csv::Reader::new());
+    codec.set_max_token_size(8);
+    let mut frames = FramedRead::new(&b"a,\"a long quoted field"[..], codec);
+    assert_eq!(frames.next().await.unwrap().unwrap().0, &b"a"[..]);
+    match frames.next().await {
+        Some(Err(csv::Error::TokenTooLong(pos))) => assert_eq!(pos, Some((1, 3, 2))),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[cfg(feature = "fallible-streaming-iterator")]
+#[test]
+fn fallible_streaming_iterator() {
+    use fallible_streaming_iterator::FallibleStreamingIterator;
+
+    let data = b"a\nbb\n\nccc\r\n";
+    let mut s = Scanner::with_capacity(&data[..], Liner::new(), 1);
+    assert_eq!(s.get(), None);
+    s.advance().unwrap();
+    assert_eq!(s.get(), Some(&b"a"[..]));
+    assert_eq!(s.token_type(), Some(&Terminator::Lf));
+    assert_eq!(s.next().unwrap(), Some(&b"bb"[..]));
+    // the buffer is used directly: the current token is gone
+    let mut byte = [0; 1];
+    s.read_exact(&mut byte).unwrap();
+    assert_eq!((&byte, s.get(), s.token_type()), (b"\n", None, None));
+    assert_eq!(s.next().unwrap(), Some(&b"ccc"[..]));
+    assert_eq!(s.token_type(), Some(&Terminator::CrLf));
+    assert_eq!(s.next().unwrap(), None);
+
+    let s = Scanner::new(&data[..], Liner::new());
+    assert_eq!(s.filter(|line| !line.is_empty()).count().unwrap(), 3);
+    let mut s = Scanner::new(&data[..], Liner::new()).skip_while(|line| line.len() < 2);
+    assert_eq!(s.next().unwrap(), Some(&b"bb"[..]));
+    assert_eq!(s.next().unwrap(), Some(&b""[..]));
+
+    // no token after an error
+    let mut s = Scanner::new(&b"a,\"b\"c\n"[..], csv::Reader::new());
+    assert_eq!(s.next().unwrap(), Some(&b"a"[..]));
+    assert!(s.advance().is_err());
+    assert_eq!((s.get(), s.token_type()), (None, None));
+}